@@ -1,10 +1,14 @@
 mod stdlib;
+mod watch;
 
 use crate::stdlib::process::set_dry_run;
+use crate::stdlib::term::{ColorMode, set_color_mode};
 use clap::Parser;
 use ruwren::{BasicFileLoader, ModuleLibrary, VMConfig};
+use std::ffi::OsString;
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "wrun")]
@@ -26,11 +30,98 @@ struct Cli {
     /// Print shell commands instead of executing them
     #[arg(long = "dry-run")]
     dry_run: bool,
+
+    /// Re-run the script when it, its imports or the given comma-separated paths change
+    #[arg(
+        long = "watch",
+        value_name = "PATHS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        requires = "script"
+    )]
+    watch: Option<String>,
+
+    /// Clear the screen before each watched run
+    #[arg(long = "clear", requires = "watch")]
+    clear: bool,
+
+    /// Seconds of quiet to wait for after a change before re-running
+    #[arg(
+        long = "debounce",
+        value_name = "SECONDS",
+        default_value_t = 0.2,
+        requires = "watch"
+    )]
+    debounce: f64,
+
+    /// When to colour output; `auto` honours NO_COLOR, FORCE_COLOR and whether output is a terminal
+    #[arg(
+        long = "color",
+        value_name = "WHEN",
+        default_value = "auto",
+        value_parser = ["auto", "always", "never"]
+    )]
+    color: String,
+}
+
+/// Arguments that reproduce this invocation for one run, minus the watch flags.
+fn run_args(cli: &Cli) -> Vec<OsString> {
+    let mut args = Vec::new();
+    if cli.dry_run {
+        args.push(OsString::from("--dry-run"));
+    }
+    if cli.color != "auto" {
+        args.push(OsString::from(format!("--color={}", cli.color)));
+    }
+    if let Some(script) = &cli.script {
+        args.push(script.clone().into_os_string());
+    }
+    if !cli.args.is_empty() {
+        args.push(OsString::from("--"));
+        args.extend(cli.args.iter().map(OsString::from));
+    }
+    args
 }
 
 fn main() -> ExitCode {
     let cli = Cli::parse();
+    if let Some(mode) = ColorMode::parse(&cli.color) {
+        set_color_mode(mode);
+    }
+
+    if let Some(paths) = &cli.watch
+        && let Some(script) = &cli.script
+    {
+        let debounce = if cli.debounce.is_finite() && cli.debounce > 0.0 {
+            Duration::from_secs_f64(cli.debounce)
+        } else {
+            Duration::ZERO
+        };
+        return watch::run(watch::WatchOptions {
+            script: script.clone(),
+            script_dir: script
+                .parent()
+                .map(|p| p.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from(".")),
+            paths: paths
+                .split(',')
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            clear: cli.clear,
+            debounce,
+            run_args: run_args(&cli),
+        });
+    }
 
+    let code = run(&cli);
+    stdlib::print::finish_output();
+    stdlib::cleanup_temp_paths();
+    code
+}
+
+fn run(cli: &Cli) -> ExitCode {
     let source = if let Some(eval_code) = &cli.eval {
         eval_code.clone()
     } else {
@@ -58,9 +149,14 @@ fn main() -> ExitCode {
     let vm = VMConfig::new()
         .library(&lib)
         .script_loader(BasicFileLoader::new().base_dir(script_dir.clone()))
+        .printer(stdlib::print::write_script_output)
         .build();
 
     let stdlib_src = stdlib::get_stdlib_source();
+    if let Err(e) = vm.interpret("wrun/bytes", stdlib_src.bytes) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
     if let Err(e) = vm.interpret("wrun/process", stdlib_src.process) {
         eprintln!("{}", e);
         return ExitCode::FAILURE;
@@ -73,6 +169,18 @@ fn main() -> ExitCode {
         eprintln!("{}", e);
         return ExitCode::FAILURE;
     }
+    if let Err(e) = vm.interpret("wrun/hash", stdlib_src.hash) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = vm.interpret("wrun/archive", stdlib_src.archive) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = vm.interpret("wrun/search", stdlib_src.search) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
     if let Err(e) = vm.interpret("wrun/env", stdlib_src.env) {
         eprintln!("{}", e);
         return ExitCode::FAILURE;
@@ -85,6 +193,14 @@ fn main() -> ExitCode {
         eprintln!("{}", e);
         return ExitCode::FAILURE;
     }
+    if let Err(e) = vm.interpret("wrun/prompt", stdlib_src.prompt) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    if let Err(e) = vm.interpret("wrun/term", stdlib_src.term) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
     if let Err(e) = vm.interpret("wrun/pipeline", stdlib_src.pipeline) {
         eprintln!("{}", e);
         return ExitCode::FAILURE;
@@ -92,10 +208,10 @@ fn main() -> ExitCode {
 
     stdlib::args::set_args(cli.args.clone());
     stdlib::print::set_script_dir(script_dir.to_string_lossy().to_string());
-    if let Some(ref script_path) = cli.script {
-        if let Some(stem) = script_path.file_stem() {
-            stdlib::print::set_script_name(stem.to_string_lossy().to_string());
-        }
+    if let Some(ref script_path) = cli.script
+        && let Some(stem) = script_path.file_stem()
+    {
+        stdlib::print::set_script_name(stem.to_string_lossy().to_string());
     }
 
     if let Err(e) = vm.interpret("main", &source) {
@@ -0,0 +1,660 @@
+//! `wrun/archive`: tar, tar.gz and zip without shelling out to host tools.
+//!
+//! Created archives are reproducible: entries are added in sorted order
+//! with one fixed mtime, permissions normalized to 0755/0644 and no owner
+//! names, so the same tree always produces the same bytes. Extraction
+//! validates every entry before writing anything, rejecting absolute
+//! paths, `..` components and symlinks that point outside the destination.
+
+#![allow(non_snake_case)]
+
+use crate::stdlib::process::dry_run_skip;
+use crate::stdlib::tree::{self, TreeOptions};
+use crate::stdlib::value::Value;
+use flate2::Compression;
+use flate2::GzBuilder;
+use flate2::read::GzDecoder;
+use globset::GlobSet;
+use ruwren::foreign_v2::WrenString;
+use ruwren::{ModuleLibrary, WrenObject, wren_impl};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// 1980-01-01T00:00:00Z, the earliest time a zip entry can carry.
+const DEFAULT_MTIME: u64 = 315_532_800;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "tar" => Some(Format::Tar),
+            "tar.gz" | "tgz" => Some(Format::TarGz),
+            "zip" => Some(Format::Zip),
+            _ => None,
+        }
+    }
+
+    /// Sniff the format from the magic bytes rather than the extension.
+    fn detect(path: &Path) -> io::Result<Format> {
+        let mut magic = [0u8; 4];
+        let mut file = fs::File::open(path)?;
+        let read = file.read(&mut magic)?;
+        Ok(match &magic[..read] {
+            [0x1f, 0x8b, ..] => Format::TarGz,
+            [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => Format::Zip,
+            _ => Format::Tar,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    File,
+    Dir,
+    Symlink,
+    Hardlink,
+    Other,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::File => "file",
+            Kind::Dir => "dir",
+            Kind::Symlink => "symlink",
+            Kind::Hardlink => "hardlink",
+            Kind::Other => "other",
+        }
+    }
+}
+
+struct Entry {
+    path: String,
+    kind: Kind,
+    size: u64,
+    mode: u32,
+    modified: u64,
+    link: Option<String>,
+}
+
+impl Entry {
+    fn into_value(self) -> Value {
+        Value::map()
+            .with("path", self.path)
+            .with("type", self.kind.name())
+            .with("size", self.size)
+            .with("mode", self.mode)
+            .with("modified", self.modified)
+            .with("link", self.link)
+    }
+}
+
+fn archive_error(path: &str, err: impl std::fmt::Display) -> String {
+    format!("Archive: {path}: {err}")
+}
+
+fn mtime_or_default(mtime: f64) -> u64 {
+    if mtime.is_finite() && mtime >= 0.0 {
+        return mtime as u64;
+    }
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_MTIME)
+}
+
+/// 0755 for directories and anything executable, 0644 otherwise.
+fn normalized_mode(meta: &fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if meta.is_dir() || meta.permissions().mode() & 0o111 != 0 {
+            0o755
+        } else {
+            0o644
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if meta.is_dir() { 0o755 } else { 0o644 }
+    }
+}
+
+/// A source entry to archive, relative to the source root.
+struct Source {
+    rel: PathBuf,
+    name: String,
+    kind: Kind,
+    mode: u32,
+}
+
+fn collect_sources(src: &Path, out: &Path, exclude: Option<GlobSet>) -> io::Result<Vec<Source>> {
+    if !src.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "source is not a directory",
+        ));
+    }
+    // An archive written inside its own source tree must not include itself.
+    let out = fs::canonicalize(out).ok();
+    let options = TreeOptions {
+        exclude,
+        ..TreeOptions::default()
+    };
+    let mut sources = Vec::new();
+    tree::walk(src, Path::new(""), &options, &mut |rel, meta| {
+        let kind = if meta.is_dir() {
+            Kind::Dir
+        } else if meta.file_type().is_symlink() {
+            Kind::Symlink
+        } else if meta.is_file() {
+            Kind::File
+        } else {
+            return;
+        };
+        if kind == Kind::File && out.is_some() && fs::canonicalize(src.join(rel)).ok() == out {
+            return;
+        }
+        let mut name = tree::display(rel);
+        if kind == Kind::Dir {
+            name.push('/');
+        }
+        sources.push(Source {
+            rel: rel.to_path_buf(),
+            name,
+            kind,
+            mode: normalized_mode(meta),
+        });
+    })?;
+    Ok(sources)
+}
+
+fn write_tar<W: Write>(writer: W, src: &Path, sources: &[Source], mtime: u64) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    for source in sources {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(source.mode);
+        header.set_mtime(mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+        let path = src.join(&source.rel);
+        match source.kind {
+            Kind::Dir => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_size(0);
+                builder.append_data(&mut header, &source.name, io::empty())?;
+            }
+            Kind::Symlink => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                let target = fs::read_link(&path)?;
+                builder.append_link(&mut header, &source.name, target)?;
+            }
+            _ => {
+                let file = fs::File::open(&path)?;
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_size(file.metadata()?.len());
+                builder.append_data(&mut header, &source.name, BufReader::new(file))?;
+            }
+        }
+    }
+    builder.into_inner()
+}
+
+fn zip_time(mtime: u64) -> zip::DateTime {
+    let Some(time) = chrono::DateTime::from_timestamp(mtime as i64, 0) else {
+        return zip::DateTime::default();
+    };
+    use chrono::{Datelike, Timelike};
+    zip::DateTime::from_date_and_time(
+        time.year().clamp(0, u16::MAX as i32) as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+fn write_zip<W: Write + Seek>(
+    writer: W,
+    src: &Path,
+    sources: &[Source],
+    mtime: u64,
+) -> io::Result<W> {
+    let mut zip = zip::ZipWriter::new(writer);
+    let time = zip_time(mtime);
+    for source in sources {
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .last_modified_time(time)
+            .unix_permissions(source.mode);
+        let path = src.join(&source.rel);
+        match source.kind {
+            Kind::Dir => zip.add_directory(&source.name, options)?,
+            Kind::Symlink => {
+                let target = fs::read_link(&path)?;
+                let target = target.to_string_lossy().replace('\\', "/");
+                zip.add_symlink(&source.name, target, options)?;
+            }
+            _ => {
+                zip.start_file(&source.name, options)?;
+                io::copy(&mut fs::File::open(&path)?, &mut zip)?;
+            }
+        }
+    }
+    Ok(zip.finish()?)
+}
+
+fn create_archive(
+    format: Format,
+    src: &Path,
+    out: &Path,
+    sources: &[Source],
+    mtime: u64,
+) -> io::Result<()> {
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = BufWriter::new(fs::File::create(out)?);
+    let file = match format {
+        Format::Tar => write_tar(file, src, sources, mtime)?,
+        Format::TarGz => {
+            // No file name and a zero header mtime keep the gzip layer stable.
+            let gz = GzBuilder::new()
+                .mtime(0)
+                .write(file, Compression::default());
+            write_tar(gz, src, sources, mtime)?.finish()?
+        }
+        Format::Zip => write_zip(file, src, sources, mtime)?,
+    };
+    file.into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()
+}
+
+fn tar_kind(entry_type: tar::EntryType) -> Kind {
+    match entry_type {
+        tar::EntryType::Regular | tar::EntryType::Continuous => Kind::File,
+        tar::EntryType::Directory => Kind::Dir,
+        tar::EntryType::Symlink => Kind::Symlink,
+        tar::EntryType::Link => Kind::Hardlink,
+        _ => Kind::Other,
+    }
+}
+
+fn zip_seconds(time: Option<zip::DateTime>) -> u64 {
+    let Some(time) = time else {
+        return 0;
+    };
+    chrono::NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)
+        .and_then(|date| {
+            date.and_hms_opt(
+                time.hour() as u32,
+                time.minute() as u32,
+                time.second() as u32,
+            )
+        })
+        .map(|time| time.and_utc().timestamp().max(0) as u64)
+        .unwrap_or(0)
+}
+
+/// Call `visit` for each entry of `path` in archive order, with a reader
+/// over its content.
+fn for_each_entry(
+    path: &Path,
+    visit: &mut dyn FnMut(Entry, &mut dyn Read) -> io::Result<()>,
+) -> io::Result<()> {
+    let format = Format::detect(path)?;
+    let file = BufReader::new(fs::File::open(path)?);
+    if format == Format::Zip {
+        let mut zip = zip::ZipArchive::new(file)?;
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index)?;
+            let kind = if file.is_dir() {
+                Kind::Dir
+            } else if file.is_symlink() {
+                Kind::Symlink
+            } else {
+                Kind::File
+            };
+            let mut link = None;
+            if kind == Kind::Symlink {
+                let mut target = String::new();
+                file.read_to_string(&mut target)?;
+                link = Some(target);
+            }
+            let default_mode = if kind == Kind::Dir { 0o755 } else { 0o644 };
+            let entry = Entry {
+                path: file.name().to_string(),
+                kind,
+                size: file.size(),
+                mode: file.unix_mode().map_or(default_mode, |mode| mode & 0o7777),
+                modified: zip_seconds(file.last_modified()),
+                link,
+            };
+            visit(entry, &mut file)?;
+        }
+        return Ok(());
+    }
+
+    let reader: Box<dyn Read> = if format == Format::TarGz {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let header = entry.header();
+        let kind = tar_kind(header.entry_type());
+        let info = Entry {
+            path: entry.path()?.to_string_lossy().into_owned(),
+            kind,
+            size: header.size().unwrap_or(0),
+            mode: header.mode().unwrap_or(0o644) & 0o7777,
+            modified: header.mtime().unwrap_or(0),
+            link: entry
+                .link_name()?
+                .map(|link| link.to_string_lossy().into_owned()),
+        };
+        visit(info, &mut entry)?;
+    }
+    Ok(())
+}
+
+/// Most symlinks followed while resolving one path, as a guard against
+/// cycles (the kernel's own limit).
+const MAX_LINK_HOPS: usize = 40;
+
+/// Checks entries in archive order, tracking the symlinks extracted so far
+/// so paths are resolved the way the file system will resolve them.
+/// Checking each link on its own is not enough: with `d -> .` in place,
+/// `e -> d/..` points at the parent of the destination.
+#[derive(Default)]
+struct EntryChecker {
+    /// Symlinks by their resolved location relative to the root.
+    links: HashMap<PathBuf, String>,
+}
+
+impl EntryChecker {
+    /// Where `path` (relative to the root) ends up once the archive's
+    /// symlinks are followed, or `None` when it leaves the root.
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let mut pending: VecDeque<Component> = path.components().collect();
+        let mut resolved = PathBuf::new();
+        let mut hops = 0;
+        while let Some(component) = pending.pop_front() {
+            match component {
+                Component::Normal(part) => {
+                    resolved.push(part);
+                    if let Some(target) = self.links.get(&resolved) {
+                        hops += 1;
+                        if hops > MAX_LINK_HOPS {
+                            return None;
+                        }
+                        resolved.pop();
+                        for part in Path::new(target).components().rev() {
+                            pending.push_front(part);
+                        }
+                    }
+                }
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !resolved.pop() {
+                        return None;
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => return None,
+            }
+        }
+        Some(resolved)
+    }
+
+    fn check(&mut self, entry: &Entry) -> Result<Option<PathBuf>, String> {
+        let unsafe_path = || format!("unsafe path '{}'", entry.path);
+        let rel = tree::relative_path(&entry.path).ok_or_else(unsafe_path)?;
+        if rel.as_os_str().is_empty() || entry.kind == Kind::Other {
+            return Ok(None);
+        }
+        let parent = rel.parent().unwrap_or(Path::new(""));
+        let location = self
+            .resolve(parent)
+            .map(|dir| dir.join(rel.file_name().unwrap_or_default()))
+            .ok_or_else(|| format!("'{}' resolves outside the destination", entry.path))?;
+        let link = entry.link.as_deref().unwrap_or_default();
+        match entry.kind {
+            Kind::Symlink => {
+                let dir = location.parent().unwrap_or(Path::new(""));
+                if self.resolve(&dir.join(link)).is_none() {
+                    return Err(format!(
+                        "symlink '{}' points outside the destination ({link})",
+                        entry.path
+                    ));
+                }
+                self.links.insert(location, link.to_string());
+            }
+            Kind::Hardlink => {
+                let target = tree::relative_path(link).filter(|p| !p.as_os_str().is_empty());
+                if target.and_then(|target| self.resolve(&target)).is_none() {
+                    return Err(format!(
+                        "hard link '{}' has unsafe target '{link}'",
+                        entry.path
+                    ));
+                }
+                self.links.remove(&location);
+            }
+            Kind::File => {
+                self.links.remove(&location);
+            }
+            Kind::Dir | Kind::Other => {}
+        }
+        Ok(Some(rel))
+    }
+}
+
+fn make_symlink(target: &str, link: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    {
+        let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn apply_mode(file: &fs::File, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(fs::Permissions::from_mode(mode & 0o777))
+}
+
+#[cfg(not(unix))]
+fn apply_mode(_file: &fs::File, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+fn extract_entry(
+    entry: &Entry,
+    rel: &Path,
+    dest: &Path,
+    root: &Path,
+    content: &mut dyn Read,
+) -> io::Result<()> {
+    let target = dest.join(rel);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+        // Never write through a symlinked directory already in `dest`.
+        if !fs::canonicalize(parent)?.starts_with(root) {
+            return Err(io::Error::other(format!(
+                "'{}' resolves outside the destination",
+                entry.path
+            )));
+        }
+    }
+    if entry.kind == Kind::Dir {
+        return fs::create_dir_all(&target);
+    }
+    if fs::symlink_metadata(&target).is_ok() {
+        tree::remove_any(&target)?;
+    }
+    let link = entry.link.as_deref().unwrap_or_default();
+    match entry.kind {
+        Kind::Symlink => make_symlink(link, &target),
+        Kind::Hardlink => {
+            let source = dest.join(tree::relative_path(link).unwrap_or_default());
+            // Hard links are made to whatever the source resolves to.
+            if !fs::canonicalize(&source)?.starts_with(root) {
+                return Err(io::Error::other(format!(
+                    "hard link '{}' resolves outside the destination",
+                    entry.path
+                )));
+            }
+            fs::hard_link(source, &target)
+        }
+        _ => {
+            let mut file = fs::File::create(&target)?;
+            io::copy(content, &mut file)?;
+            apply_mode(&file, entry.mode)?;
+            let modified = UNIX_EPOCH + Duration::from_secs(entry.modified);
+            file.set_times(fs::FileTimes::new().set_modified(modified))
+        }
+    }
+}
+
+#[derive(WrenObject, Default)]
+pub struct Archive;
+
+#[wren_impl]
+impl Archive {
+    /// Archive the contents of `src` into `out`; returns the entry names.
+    /// `exclude` is a `\x00`-joined glob list and a negative `mtime` means
+    /// `SOURCE_DATE_EPOCH`, else 1980-01-01.
+    fn create_(
+        &self,
+        format: WrenString,
+        src: WrenString,
+        out: WrenString,
+        exclude: WrenString,
+        mtime: f64,
+    ) -> Result<Vec<String>, String> {
+        let format_name = format.into_string().unwrap_or_default();
+        let src = src.into_string().unwrap_or_default();
+        let out = out.into_string().unwrap_or_default();
+        let exclude = exclude.into_string().unwrap_or_default();
+        let Some(format) = Format::from_name(&format_name) else {
+            return Err(format!("Archive: unknown format '{format_name}'"));
+        };
+
+        let exclude = tree::build_excludes(&exclude).map_err(|err| format!("Archive: {err}"))?;
+        let sources = collect_sources(Path::new(&src), Path::new(&out), exclude)
+            .map_err(|err| archive_error(&src, err))?;
+        let names = sources.iter().map(|s| s.name.clone()).collect();
+        if dry_run_skip(format_args!(
+            "create {out} ({} entries from {src})",
+            sources.len()
+        )) {
+            return Ok(names);
+        }
+        let created = create_archive(
+            format,
+            Path::new(&src),
+            Path::new(&out),
+            &sources,
+            mtime_or_default(mtime),
+        );
+        if let Err(err) = created {
+            let _ = fs::remove_file(&out);
+            return Err(archive_error(&out, err));
+        }
+        Ok(names)
+    }
+
+    /// Entries of a tar, tar.gz or zip archive (format detected from content).
+    fn list(&self, archive: WrenString) -> Result<Value, String> {
+        let archive = archive.into_string().unwrap_or_default();
+        let mut entries = Vec::new();
+        for_each_entry(Path::new(&archive), &mut |entry, _| {
+            entries.push(entry.into_value());
+            Ok(())
+        })
+        .map_err(|err| archive_error(&archive, err))?;
+        Ok(Value::List(entries))
+    }
+
+    /// Extract into `dest` (created if missing); returns the extracted
+    /// paths. Nothing is written if any entry is unsafe.
+    fn extract(&self, archive: WrenString, dest: WrenString) -> Result<Vec<String>, String> {
+        let archive = archive.into_string().unwrap_or_default();
+        let dest = PathBuf::from(dest.into_string().unwrap_or_default());
+
+        let mut planned = Vec::new();
+        let mut problem = None;
+        let mut checker = EntryChecker::default();
+        for_each_entry(Path::new(&archive), &mut |entry, _| {
+            match checker.check(&entry) {
+                Ok(Some(rel)) => {
+                    let mut name = tree::display(&rel);
+                    if entry.kind == Kind::Dir {
+                        name.push('/');
+                    }
+                    planned.push(name);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    problem.get_or_insert(err);
+                }
+            }
+            Ok(())
+        })
+        .map_err(|err| archive_error(&archive, err))?;
+        if let Some(problem) = problem {
+            return Err(archive_error(&archive, problem));
+        }
+        if dry_run_skip(format_args!(
+            "extract {archive} -> {} ({} entries)",
+            dest.display(),
+            planned.len()
+        )) {
+            return Ok(planned);
+        }
+
+        fs::create_dir_all(&dest).map_err(|err| archive_error(&dest.to_string_lossy(), err))?;
+        let root = fs::canonicalize(&dest).map_err(|err| archive_error(&archive, err))?;
+        let mut checker = EntryChecker::default();
+        for_each_entry(Path::new(&archive), &mut |entry, content| {
+            let Ok(Some(rel)) = checker.check(&entry) else {
+                return Ok(());
+            };
+            extract_entry(&entry, &rel, &dest, &root, content)
+        })
+        .map_err(|err| archive_error(&archive, err))?;
+        Ok(planned)
+    }
+}
+
+ruwren::wren_module! {
+    mod wrun_archive {
+        pub crate::stdlib::archive::Archive;
+    }
+}
+
+pub fn publish_module(lib: &mut ModuleLibrary) {
+    wrun_archive::publish_module(lib);
+}
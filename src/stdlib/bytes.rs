@@ -0,0 +1,217 @@
+#![allow(non_snake_case)]
+
+use crate::stdlib::value::RawText;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ruwren::foreign_v2::WrenString;
+use ruwren::{ModuleLibrary, WrenObject, wren_impl};
+
+/// A byte buffer backed by `Vec<u8>`, for binary data that would be too
+/// slow/large as a List of Nums.
+#[derive(WrenObject, Default, Clone)]
+pub struct Bytes {
+    data: Vec<u8>,
+}
+
+impl Bytes {
+    pub fn from_vec(data: Vec<u8>) -> Self {
+        Bytes { data }
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+fn index_error(index: f64, len: usize) -> String {
+    format!("Bytes: index {index} out of bounds (count {len})")
+}
+
+/// Validate a Wren index (already made non-negative on the Wren side) for a
+/// `width`-byte access.
+fn checked_index(index: f64, width: usize, len: usize) -> Result<usize, String> {
+    if !index.is_finite() || index < 0.0 || index.fract() != 0.0 {
+        return Err(index_error(index, len));
+    }
+    let start = index as usize;
+    if start.checked_add(width).is_none_or(|end| end > len) {
+        return Err(index_error(index, len));
+    }
+    Ok(start)
+}
+
+fn int_width(width: f64) -> Result<usize, String> {
+    match width as usize {
+        w @ (1 | 2 | 4) if width.fract() == 0.0 => Ok(w),
+        _ => Err(format!("Bytes: unsupported integer width {width}")),
+    }
+}
+
+#[wren_impl]
+impl Bytes {
+    /// `size` zero bytes.
+    #[wren_impl(constructor)]
+    fn create(&self, size: f64) -> Result<BytesInstance, String> {
+        if !size.is_finite() || size < 0.0 {
+            return Err(format!("Bytes: invalid size {size}"));
+        }
+        Ok(BytesInstance {
+            data: vec![0; size as usize],
+        })
+    }
+
+    /// The raw bytes of a Wren string (no re-encoding).
+    fn fromString(&self, text: WrenString) -> Bytes {
+        Bytes::from_vec(text.bytes().to_vec())
+    }
+
+    fn fromHex(&self, text: WrenString) -> Result<Bytes, String> {
+        let text = String::from_utf8_lossy(text.bytes());
+        let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        hex::decode(digits)
+            .map(Bytes::from_vec)
+            .map_err(|err| format!("Bytes: invalid hex: {err}"))
+    }
+
+    fn fromBase64(&self, text: WrenString) -> Result<Bytes, String> {
+        let text: Vec<u8> = text
+            .bytes()
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        BASE64
+            .decode(text)
+            .map(Bytes::from_vec)
+            .map_err(|err| format!("Bytes: invalid base64: {err}"))
+    }
+
+    #[wren_impl(instance, getter)]
+    fn count(&self) -> f64 {
+        self.data.len() as f64
+    }
+
+    #[wren_impl(instance)]
+    fn byteAt_(&self, index: f64) -> Result<f64, String> {
+        let index = checked_index(index, 1, self.data.len())?;
+        Ok(self.data[index] as f64)
+    }
+
+    #[wren_impl(instance)]
+    fn setByteAt_(&mut self, index: f64, value: f64) -> Result<f64, String> {
+        let index = checked_index(index, 1, self.data.len())?;
+        if !(0.0..=255.0).contains(&value) || value.fract() != 0.0 {
+            return Err(format!("Bytes: byte value {value} out of range 0..255"));
+        }
+        self.data[index] = value as u8;
+        Ok(value)
+    }
+
+    /// Copy of `[start, end)`; bounds are clamped (the Wren side resolves
+    /// negative indices and ranges).
+    #[wren_impl(instance)]
+    fn slice_(&self, start: f64, end: f64) -> Bytes {
+        let len = self.data.len();
+        let clamp = |v: f64| (v.max(0.0) as usize).min(len);
+        let (start, end) = (clamp(start), clamp(end));
+        Bytes::from_vec(self.data[start..end.max(start)].to_vec())
+    }
+
+    #[wren_impl(instance, object(other))]
+    fn concat_(&self, other: Option<Bytes>) -> Result<Bytes, String> {
+        let Some(other) = other else {
+            return Err("Bytes: can only concatenate Bytes".to_string());
+        };
+        let mut data = Vec::with_capacity(self.data.len() + other.data.len());
+        data.extend_from_slice(&self.data);
+        data.extend_from_slice(&other.data);
+        Ok(Bytes::from_vec(data))
+    }
+
+    #[wren_impl(instance, object(other))]
+    fn equals_(&self, other: Option<Bytes>) -> bool {
+        other.is_some_and(|other| other.data == self.data)
+    }
+
+    #[wren_impl(instance, getter)]
+    fn toHex(&self) -> String {
+        hex::encode(&self.data)
+    }
+
+    #[wren_impl(instance, getter)]
+    fn toBase64(&self) -> String {
+        BASE64.encode(&self.data)
+    }
+
+    #[wren_impl(instance, getter)]
+    fn toList(&self) -> Vec<f64> {
+        self.data.iter().map(|&b| b as f64).collect()
+    }
+
+    /// Decode to a Wren string: `utf8` (invalid sequences become U+FFFD),
+    /// `latin1`, `hex`, `base64`, or `binary` (the bytes unchanged).
+    #[wren_impl(instance)]
+    fn decode_(&self, encoding: WrenString) -> Result<RawText, String> {
+        let encoding = String::from_utf8_lossy(encoding.bytes()).to_ascii_lowercase();
+        let text = match encoding.replace('-', "").as_str() {
+            "utf8" => String::from_utf8_lossy(&self.data).into_owned().into_bytes(),
+            "latin1" => self
+                .data
+                .iter()
+                .map(|&b| b as char)
+                .collect::<String>()
+                .into_bytes(),
+            "hex" => hex::encode(&self.data).into_bytes(),
+            "base64" => BASE64.encode(&self.data).into_bytes(),
+            "binary" => self.data.clone(),
+            _ => return Err(format!("Bytes: unknown encoding '{encoding}'")),
+        };
+        Ok(RawText(text))
+    }
+
+    /// Unsigned integer of `width` bytes (1, 2 or 4) at `offset`.
+    #[wren_impl(instance)]
+    fn readUInt_(&self, offset: f64, width: f64, big_endian: bool) -> Result<f64, String> {
+        let width = int_width(width)?;
+        let start = checked_index(offset, width, self.data.len())?;
+        let bytes = &self.data[start..start + width];
+        let value = if big_endian {
+            bytes.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32)
+        } else {
+            bytes.iter().rev().fold(0u32, |acc, &b| (acc << 8) | b as u32)
+        };
+        Ok(value as f64)
+    }
+
+    #[wren_impl(instance)]
+    fn writeUInt_(
+        &mut self,
+        offset: f64,
+        width: f64,
+        big_endian: bool,
+        value: f64,
+    ) -> Result<f64, String> {
+        let width = int_width(width)?;
+        let start = checked_index(offset, width, self.data.len())?;
+        let max = (1u64 << (8 * width)) - 1;
+        if value < 0.0 || value > max as f64 || value.fract() != 0.0 {
+            return Err(format!("Bytes: value {value} does not fit in {width} byte(s)"));
+        }
+        let value = value as u32;
+        for i in 0..width {
+            let shift = if big_endian { width - 1 - i } else { i };
+            self.data[start + i] = (value >> (8 * shift)) as u8;
+        }
+        Ok(value as f64)
+    }
+}
+
+ruwren::wren_module! {
+    mod wrun_bytes {
+        pub crate::stdlib::bytes::Bytes;
+    }
+}
+
+pub fn publish_module(lib: &mut ModuleLibrary) {
+    wrun_bytes::publish_module(lib);
+}
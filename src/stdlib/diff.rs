@@ -0,0 +1,910 @@
+//! Pretty diff rendering shared by `Diff.pretty`, watcher events and
+//! dry-run output, plus the directory comparison behind `Diff.dirs`, the
+//! plain/HTML output formats and `Diff.hunks`.
+//!
+//! Line diffs render as a unified or side-by-side view with line numbers.
+//! Changed words inside changed lines can be emphasised, and lines can be
+//! truncated or wrapped to a maximum width.
+
+use crate::stdlib::layout::char_width;
+use crate::stdlib::term;
+use crate::stdlib::tree::{self, EntryKind, TreeChange, TreeDiffEntry, TreeOptions};
+use crate::stdlib::value::Value;
+use diffy::create_patch;
+use sha2::{Digest, Sha256};
+use similar::{Algorithm, ChangeTag, DiffOp, TextDiff};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Narrowest text column a width limit may squeeze a line into.
+const MIN_COLUMN: usize = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    Unified,
+    SideBySide,
+}
+
+impl Layout {
+    pub fn parse(name: &str) -> Layout {
+        match name.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "sidebyside" | "split" => Layout::SideBySide,
+            _ => Layout::Unified,
+        }
+    }
+}
+
+/// What happens to lines longer than `max_width`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// Cut the line and end it with `…`.
+    Truncate,
+    /// Continue on extra rows marked with `↪`.
+    Wrap,
+}
+
+impl Overflow {
+    pub fn parse(name: &str) -> Overflow {
+        match name.to_ascii_lowercase().as_str() {
+            "wrap" => Overflow::Wrap,
+            _ => Overflow::Truncate,
+        }
+    }
+}
+
+pub struct PrettyOptions<'a> {
+    pub granularity: &'a str,
+    pub algorithm: &'a str,
+    pub layout: Layout,
+    /// Widest a rendered line may be, gutter included; `None` is unlimited.
+    pub max_width: Option<usize>,
+    pub overflow: Overflow,
+    /// Emphasise the changed words inside changed lines.
+    pub highlight: bool,
+}
+
+impl Default for PrettyOptions<'_> {
+    fn default() -> Self {
+        PrettyOptions {
+            granularity: "line",
+            algorithm: "myers",
+            layout: Layout::Unified,
+            max_width: None,
+            overflow: Overflow::Truncate,
+            highlight: true,
+        }
+    }
+}
+
+/// Files with a NUL byte in their first 8 KB are treated as binary.
+const BINARY_PROBE: usize = 8 * 1024;
+
+/// Content that is summarised instead of diffed: a NUL byte near the start
+/// or invalid UTF-8.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_PROBE)].contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+fn short_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))[..12].to_string()
+}
+
+/// `(10 → 12 bytes, sha256 1a2b… → 3c4d…)`; a missing side is left out.
+fn binary_detail(before: Option<&[u8]>, after: Option<&[u8]>) -> String {
+    let size = |bytes: Option<&[u8]>| bytes.map(|bytes| bytes.len().to_string());
+    let hash = |bytes: Option<&[u8]>| bytes.map(|bytes| format!("{}…", short_hash(bytes)));
+    let pair = |old: Option<String>, new: Option<String>| match (old, new) {
+        (Some(old), Some(new)) => format!("{old} → {new}"),
+        (old, new) => old.or(new).unwrap_or_default(),
+    };
+    format!(
+        "({} bytes, sha256 {})",
+        pair(size(before), size(after)),
+        pair(hash(before), hash(after))
+    )
+}
+
+/// The pretty view of a binary change: sizes and hashes instead of lines.
+pub fn pretty_binary(path: &str, before: &[u8], after: &[u8]) -> String {
+    let path = pretty_path(path);
+    if before == after {
+        return format!(
+            "• Binary file {path} unchanged \x1b[90m({} bytes)\x1b[0m\n",
+            before.len()
+        );
+    }
+    let detail = binary_detail(Some(before), Some(after));
+    format!("• Binary files {path} differ \x1b[90m{detail}\x1b[0m\n")
+}
+
+/// What `git diff` prints for a binary change; empty when nothing changed.
+pub fn binary_patch(path: &str, before: &[u8], after: &[u8]) -> String {
+    if before == after {
+        return String::new();
+    }
+    let path = pretty_path(path);
+    format!("Binary files a/{path} and b/{path} differ\n")
+}
+
+/// Output format for the pretty renderers, which all emit ANSI colour.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Ansi,
+    /// Escapes stripped, for log files.
+    Plain,
+    /// A `<pre class="wrun-diff">` block with the colours as classes.
+    Html,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Format {
+        match name.to_ascii_lowercase().as_str() {
+            "plain" | "text" => Format::Plain,
+            "html" => Format::Html,
+            "auto" if !term::use_color(term::Stream::Stdout) => Format::Plain,
+            _ => Format::Ansi,
+        }
+    }
+}
+
+/// Classes used by `Format::Html`, for reports to embed.
+pub const HTML_STYLE: &str = "\
+.wrun-diff { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; line-height: 1.4; }
+.wrun-diff .del { color: #b31d28; background: #ffeef0; }
+.wrun-diff .ins { color: #22863a; background: #f0fff4; }
+.wrun-diff .em { font-weight: bold; text-decoration: underline; }
+.wrun-diff .mark { color: #b08800; font-weight: bold; }
+.wrun-diff .dim { color: #6a737d; }
+";
+
+/// Split ANSI-coloured text into plain text and SGR parameter strings.
+fn ansi_pieces(text: &str) -> Vec<Result<&str, &str>> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        if start > 0 {
+            pieces.push(Ok(&rest[..start]));
+        }
+        let after = &rest[start + 2..];
+        match after.find('m') {
+            Some(end) => {
+                pieces.push(Err(&after[..end]));
+                rest = &after[end + 1..];
+            }
+            None => {
+                rest = after;
+            }
+        }
+    }
+    if !rest.is_empty() {
+        pieces.push(Ok(rest));
+    }
+    pieces
+}
+
+fn html_escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Convert the output of a pretty renderer to `format`.
+pub fn render(text: &str, format: Format) -> String {
+    match format {
+        Format::Ansi => text.to_string(),
+        Format::Plain => ansi_pieces(text)
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect(),
+        Format::Html => {
+            let mut out = String::from("<pre class=\"wrun-diff\">");
+            let mut open: Vec<&str> = Vec::new();
+            for piece in ansi_pieces(text) {
+                match piece {
+                    Ok(text) => html_escape(text, &mut out),
+                    Err("0") | Err("") => {
+                        for _ in open.drain(..) {
+                            out.push_str("</span>");
+                        }
+                    }
+                    Err("27") => {
+                        // Close back to (and including) the emphasis span.
+                        while let Some(class) = open.pop() {
+                            out.push_str("</span>");
+                            if class == "em" {
+                                break;
+                            }
+                        }
+                    }
+                    Err(code) => {
+                        let class = match code {
+                            "31" => "del",
+                            "32" => "ins",
+                            "7" => "em",
+                            "33" | "1;33" => "mark",
+                            "90" => "dim",
+                            _ => continue,
+                        };
+                        let _ = write!(out, "<span class=\"{class}\">");
+                        open.push(class);
+                    }
+                }
+            }
+            for _ in open {
+                out.push_str("</span>");
+            }
+            out.push_str("</pre>\n");
+            out
+        }
+    }
+}
+
+/// Unified-diff hunks as data: `oldStart`/`oldLines`/`newStart`/`newLines`
+/// as in the `@@` header, and one map per line with its `type`
+/// (`context`, `add`, `remove`), `text` and line numbers.
+pub fn hunks(before: &str, after: &str, context: usize, algorithm: &str) -> Value {
+    let diff = make_diff(before, after, "line", algorithm);
+    let mut hunks = Vec::new();
+    for group in diff.grouped_ops(context) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_start = first.old_range().start;
+        let old_len = last.old_range().end - old_start;
+        let new_start = first.new_range().start;
+        let new_len = last.new_range().end - new_start;
+        // Unified diffs number an empty range by the line before it.
+        let header_start = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+        let range = |start: usize, len: usize| {
+            if len == 1 {
+                start.to_string()
+            } else {
+                format!("{start},{len}")
+            }
+        };
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => "context",
+                    ChangeTag::Insert => "add",
+                    ChangeTag::Delete => "remove",
+                };
+                let value = change.value();
+                lines.push(
+                    Value::map()
+                        .with("type", kind)
+                        .with("text", value.strip_suffix('\n').unwrap_or(value))
+                        .with("oldLine", change.old_index().map(|idx| idx + 1))
+                        .with("newLine", change.new_index().map(|idx| idx + 1)),
+                );
+            }
+        }
+        let old_header = header_start(old_start, old_len);
+        let new_header = header_start(new_start, new_len);
+        hunks.push(
+            Value::map()
+                .with(
+                    "header",
+                    format!(
+                        "@@ -{} +{} @@",
+                        range(old_header, old_len),
+                        range(new_header, new_len)
+                    ),
+                )
+                .with("oldStart", old_header)
+                .with("oldLines", old_len)
+                .with("newStart", new_header)
+                .with("newLines", new_len)
+                .with("lines", Value::List(lines)),
+        );
+    }
+    Value::List(hunks)
+}
+
+pub fn pretty_path(path: &str) -> String {
+    if path.is_empty() {
+        "file".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+fn granularity_name(name: &str) -> &str {
+    match name.to_ascii_lowercase().as_str() {
+        "word" => "word",
+        "char" => "char",
+        _ => "line",
+    }
+}
+
+fn algorithm_name(name: &str) -> &str {
+    match name.to_ascii_lowercase().as_str() {
+        "patience" => "patience",
+        "lcs" => "lcs",
+        _ => "myers",
+    }
+}
+
+fn make_diff<'a>(
+    before: &'a str,
+    after: &'a str,
+    granularity: &str,
+    algorithm: &str,
+) -> TextDiff<'a, 'a, 'a, str> {
+    let mut cfg = TextDiff::configure();
+    let algorithm = match algorithm_name(algorithm) {
+        "patience" => Algorithm::Patience,
+        "lcs" => Algorithm::Lcs,
+        _ => Algorithm::Myers,
+    };
+    cfg.algorithm(algorithm);
+    match granularity_name(granularity) {
+        "word" => cfg.diff_words(before, after),
+        "char" => cfg.diff_chars(before, after),
+        _ => cfg.diff_lines(before, after),
+    }
+}
+
+fn format_line_number(idx: Option<usize>, width: usize) -> String {
+    match idx {
+        Some(v) => format!("{:>width$}", v + 1, width = width),
+        None => " ".repeat(width),
+    }
+}
+
+/// A line's text as runs; `true` marks runs to emphasise.
+type Runs = Vec<(bool, String)>;
+
+struct Line {
+    tag: ChangeTag,
+    old: Option<usize>,
+    new: Option<usize>,
+    runs: Runs,
+}
+
+/// Terminal cells taken by `runs`.
+fn runs_len(runs: &Runs) -> usize {
+    runs.iter()
+        .flat_map(|(_, text)| text.chars())
+        .map(char_width)
+        .sum()
+}
+
+/// Lines of one hunk group, without their trailing newlines.
+fn group_lines<'a>(
+    diff: &'a TextDiff<'a, 'a, 'a, str>,
+    group: &[DiffOp],
+    highlight: bool,
+    expand_tabs: bool,
+) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for op in group {
+        let changes: Vec<(ChangeTag, Option<usize>, Option<usize>, Runs)> = if highlight {
+            diff.iter_inline_changes(op)
+                .map(|change| {
+                    let runs = change
+                        .iter_strings_lossy()
+                        .map(|(emph, text)| (emph, text.into_owned()))
+                        .collect();
+                    (change.tag(), change.old_index(), change.new_index(), runs)
+                })
+                .collect()
+        } else {
+            diff.iter_changes(op)
+                .map(|change| {
+                    let runs = vec![(false, change.value().to_string())];
+                    (change.tag(), change.old_index(), change.new_index(), runs)
+                })
+                .collect()
+        };
+        for (tag, old, new, mut runs) in changes {
+            if let Some((_, last)) = runs.last_mut()
+                && last.ends_with('\n')
+            {
+                last.pop();
+            }
+            if expand_tabs {
+                for (_, text) in runs.iter_mut() {
+                    *text = text.replace('\t', "    ");
+                }
+            }
+            runs.retain(|(_, text)| !text.is_empty());
+            let (old, new) = match tag {
+                ChangeTag::Delete => (old, None),
+                ChangeTag::Insert => (None, new),
+                ChangeTag::Equal => (old, new),
+            };
+            lines.push(Line {
+                tag,
+                old,
+                new,
+                runs,
+            });
+        }
+    }
+    lines
+}
+
+/// `runs` cut into rows of at most `width` cells. A wide character that
+/// would straddle the edge starts the next row instead.
+fn split_runs(runs: &Runs, width: usize) -> Vec<Runs> {
+    let mut rows = Vec::new();
+    let mut row = Runs::new();
+    let mut used = 0;
+    for (emph, text) in runs {
+        for c in text.chars() {
+            let cells = char_width(c);
+            if used + cells > width && used > 0 {
+                rows.push(std::mem::take(&mut row));
+                used = 0;
+            }
+            match row.last_mut() {
+                Some((last, part)) if last == emph => part.push(c),
+                _ => row.push((*emph, c.to_string())),
+            }
+            used += cells;
+        }
+    }
+    rows.push(row);
+    rows
+}
+
+/// Rows of at most `width` cells for one line.
+fn fit(runs: &Runs, width: Option<usize>, overflow: Overflow) -> Vec<Runs> {
+    let total = runs_len(runs);
+    let Some(width) = width.filter(|&width| total > width) else {
+        return vec![runs.clone()];
+    };
+    match overflow {
+        Overflow::Truncate => {
+            let mut row = split_runs(runs, width - 1).swap_remove(0);
+            row.push((false, "…".to_string()));
+            vec![row]
+        }
+        Overflow::Wrap => split_runs(runs, width),
+    }
+}
+
+/// `sign` and `runs` in `color`, with emphasised runs in reverse video.
+fn paint(sign: &str, runs: &Runs, color: &str) -> String {
+    let mut text = sign.to_string();
+    for (emph, run) in runs {
+        if *emph {
+            let _ = write!(text, "\x1b[7m{run}\x1b[27m");
+        } else {
+            text.push_str(run);
+        }
+    }
+    if color.is_empty() {
+        text
+    } else {
+        format!("{color}{text}\x1b[0m")
+    }
+}
+
+fn sign_and_color(tag: ChangeTag) -> (&'static str, &'static str) {
+    match tag {
+        ChangeTag::Delete => ("-", "\x1b[31m"),
+        ChangeTag::Insert => ("+", "\x1b[32m"),
+        ChangeTag::Equal => (" ", ""),
+    }
+}
+
+const CONTINUATION: &str = "\x1b[90m↪\x1b[0m";
+
+fn render_unified(out: &mut String, lines: &[Line], number_width: usize, options: &PrettyOptions) {
+    // "  old new " plus the sign column.
+    let gutter = 2 + number_width + 1 + number_width + 1 + 1;
+    let column = options
+        .max_width
+        .map(|width| width.saturating_sub(gutter).max(MIN_COLUMN));
+    let blank = " ".repeat(number_width);
+    for line in lines {
+        let (sign, color) = sign_and_color(line.tag);
+        let old_num = format_line_number(line.old, number_width);
+        let new_num = format_line_number(line.new, number_width);
+        for (idx, row) in fit(&line.runs, column, options.overflow).iter().enumerate() {
+            if idx == 0 {
+                let _ = write!(out, "  \x1b[90m{old_num}\x1b[0m \x1b[90m{new_num}\x1b[0m ");
+                let _ = writeln!(out, "{}", paint(sign, row, color));
+            } else {
+                let _ = write!(out, "  {blank} {blank} {CONTINUATION}");
+                let _ = writeln!(out, "{}", paint("", row, color));
+            }
+        }
+    }
+}
+
+/// Pair each run of deletions with the insertions that follow it, so
+/// replaced lines sit next to their replacements.
+fn side_by_side_pairs(lines: &[Line]) -> Vec<(Option<&Line>, Option<&Line>)> {
+    let mut pairs = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        if lines[idx].tag == ChangeTag::Equal {
+            pairs.push((Some(&lines[idx]), Some(&lines[idx])));
+            idx += 1;
+            continue;
+        }
+        let deleted_start = idx;
+        while idx < lines.len() && lines[idx].tag == ChangeTag::Delete {
+            idx += 1;
+        }
+        let inserted_start = idx;
+        while idx < lines.len() && lines[idx].tag == ChangeTag::Insert {
+            idx += 1;
+        }
+        let deleted = &lines[deleted_start..inserted_start];
+        let inserted = &lines[inserted_start..idx];
+        for row in 0..deleted.len().max(inserted.len()) {
+            pairs.push((deleted.get(row), inserted.get(row)));
+        }
+    }
+    pairs
+}
+
+/// One half of a side-by-side row: number, sign and text padded to `column`.
+fn half_row(
+    line: Option<&Line>,
+    row: Option<&Runs>,
+    first: bool,
+    old_side: bool,
+    width: usize,
+    column: usize,
+) -> String {
+    let mut out = String::new();
+    let (number, sign, color) = match line {
+        Some(line) => {
+            let (sign, color) = sign_and_color(line.tag);
+            let number = if old_side { line.old } else { line.new };
+            (number, sign, color)
+        }
+        None => (None, " ", ""),
+    };
+    let empty = Vec::new();
+    let row = row.unwrap_or(&empty);
+    if first {
+        let _ = write!(out, "\x1b[90m{}\x1b[0m ", format_line_number(number, width));
+        out.push_str(&paint(sign, row, color));
+    } else {
+        let _ = write!(out, "{} ", " ".repeat(width));
+        if row.is_empty() {
+            out.push(' ');
+        } else {
+            out.push_str(CONTINUATION);
+        }
+        out.push_str(&paint("", row, color));
+    }
+    out.push_str(&" ".repeat(column.saturating_sub(runs_len(row))));
+    out
+}
+
+fn render_side_by_side(
+    out: &mut String,
+    lines: &[Line],
+    number_width: usize,
+    column: usize,
+    options: &PrettyOptions,
+) {
+    for (left, right) in side_by_side_pairs(lines) {
+        let fitted = |line: Option<&Line>| {
+            line.map(|line| fit(&line.runs, Some(column), options.overflow))
+                .unwrap_or_default()
+        };
+        let left_rows = fitted(left);
+        let right_rows = fitted(right);
+        for idx in 0..left_rows.len().max(right_rows.len()).max(1) {
+            let first = idx == 0;
+            let left_half = half_row(left, left_rows.get(idx), first, true, number_width, column);
+            let right_half = half_row(
+                right,
+                right_rows.get(idx),
+                first,
+                false,
+                number_width,
+                column,
+            );
+            let _ = writeln!(
+                out,
+                "  {left_half} \x1b[90m│\x1b[0m {}",
+                right_half.trim_end()
+            );
+        }
+    }
+}
+
+pub fn pretty_diff_text(path: &str, before: &str, after: &str, options: &PrettyOptions) -> String {
+    let path = pretty_path(path);
+    let diff = make_diff(before, after, options.granularity, options.algorithm);
+
+    let mut added = 0usize;
+    let mut removed = 0usize;
+    let mut max_old = 1usize;
+    let mut max_new = 1usize;
+    for change in diff.iter_all_changes() {
+        if let Some(old_idx) = change.old_index() {
+            max_old = max_old.max(old_idx + 1);
+        }
+        if let Some(new_idx) = change.new_index() {
+            max_new = max_new.max(new_idx + 1);
+        }
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let mut out = String::new();
+    let mode = granularity_name(options.granularity);
+    let algo = algorithm_name(options.algorithm);
+    let _ = writeln!(
+        &mut out,
+        "• Edited {path} (\x1b[32m+{added}\x1b[0m \x1b[31m-{removed}\x1b[0m) [{mode}/{algo}]"
+    );
+
+    if mode == "line" {
+        let line_number_width = max_old.max(max_new).to_string().len();
+        let side_by_side = options.layout == Layout::SideBySide;
+        let expand_tabs = side_by_side || options.max_width.is_some();
+        let groups: Vec<Vec<Line>> = diff
+            .grouped_ops(2)
+            .iter()
+            .map(|group| group_lines(&diff, group, options.highlight, expand_tabs))
+            .collect();
+        // Side by side: "  old ±text │ new ±text", halves sharing the width.
+        let column = match options.max_width {
+            Some(width) => (width.saturating_sub(9 + 2 * line_number_width) / 2).max(MIN_COLUMN),
+            None => groups
+                .iter()
+                .flatten()
+                .map(|line| runs_len(&line.runs))
+                .max()
+                .unwrap_or(0),
+        };
+        let elision_indent = 2 + line_number_width + 1 + line_number_width + 1;
+        for (idx, lines) in groups.iter().enumerate() {
+            if idx > 0 {
+                let _ = writeln!(&mut out, "{}\x1b[90m⋮\x1b[0m", " ".repeat(elision_indent));
+            }
+            if side_by_side {
+                render_side_by_side(&mut out, lines, line_number_width, column, options);
+            } else {
+                render_unified(&mut out, lines, line_number_width, options);
+            }
+        }
+    } else {
+        let _ = writeln!(
+            &mut out,
+            "    \x1b[90mGranularity: {mode} (inline token view)\x1b[0m"
+        );
+        let _ = write!(&mut out, "    ");
+        for change in diff.iter_all_changes() {
+            match change.tag() {
+                ChangeTag::Delete => {
+                    let _ = write!(&mut out, "\x1b[31m{}\x1b[0m", change.value());
+                }
+                ChangeTag::Insert => {
+                    let _ = write!(&mut out, "\x1b[32m{}\x1b[0m", change.value());
+                }
+                ChangeTag::Equal => {
+                    let _ = write!(&mut out, "{}", change.value());
+                }
+            }
+        }
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// File content for a text diff, or `None` for binary and unreadable files.
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    if is_binary(&bytes) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Unified diff with git-style headers; a missing side is `/dev/null`.
+fn unified_patch(path: &str, before: Option<&str>, after: Option<&str>) -> String {
+    let old = before.map_or("/dev/null".to_string(), |_| format!("a/{path}"));
+    let new = after.map_or("/dev/null".to_string(), |_| format!("b/{path}"));
+    create_patch(before.unwrap_or(""), after.unwrap_or(""))
+        .to_string()
+        .replacen("--- original", &format!("--- {old}"), 1)
+        .replacen("+++ modified", &format!("+++ {new}"), 1)
+}
+
+/// One `Diff.dirs` entry; with `content`, the text of each side that is
+/// a text file (a side is `None` when it is absent or not a file).
+struct DirEntryDiff {
+    entry: TreeDiffEntry,
+    path: String,
+    texts: Option<(Option<String>, Option<String>)>,
+    binary: bool,
+}
+
+fn compare_dirs(
+    left: &Path,
+    right: &Path,
+    exclude: &str,
+    content: bool,
+) -> Result<Vec<DirEntryDiff>, String> {
+    for root in [left, right] {
+        if !root.is_dir() {
+            return Err(format!("Diff: {}: not a directory", root.display()));
+        }
+    }
+    let options = TreeOptions {
+        exclude: tree::build_excludes(exclude).map_err(|err| format!("Diff: {err}"))?,
+        ..TreeOptions::default()
+    };
+    let entries = tree::compare_trees(left, right, &options)
+        .map_err(|err| format!("Diff: comparing {}: {err}", left.display()))?;
+
+    let side = |root: &Path, rel: &Path, kind: Option<EntryKind>| {
+        // `Err(())` marks a file that exists but is not text.
+        match kind {
+            Some(EntryKind::File) => read_text(&root.join(rel)).map(Some).ok_or(()),
+            _ => Ok(None),
+        }
+    };
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let path = tree::display(&entry.rel);
+            let has_file =
+                entry.left == Some(EntryKind::File) || entry.right == Some(EntryKind::File);
+            let (texts, binary) = if content && has_file {
+                match (
+                    side(left, &entry.rel, entry.left),
+                    side(right, &entry.rel, entry.right),
+                ) {
+                    (Ok(before), Ok(after)) => (Some((before, after)), false),
+                    _ => (None, true),
+                }
+            } else {
+                (None, false)
+            };
+            DirEntryDiff {
+                entry,
+                path,
+                texts,
+                binary,
+            }
+        })
+        .collect())
+}
+
+/// `Diff.dirs`: added/removed/changed/type-changed paths of `right`
+/// relative to `left`, with per-file unified diffs when `content` is set.
+pub fn dirs_value(
+    left: &Path,
+    right: &Path,
+    exclude: &str,
+    content: bool,
+) -> Result<Value, String> {
+    let diffs = compare_dirs(left, right, exclude, content)?;
+    let paths = |change: TreeChange| -> Vec<String> {
+        diffs
+            .iter()
+            .filter(|diff| diff.entry.change == change)
+            .map(|diff| diff.path.clone())
+            .collect()
+    };
+    let added = paths(TreeChange::Added);
+    let removed = paths(TreeChange::Removed);
+    let changed = paths(TreeChange::Changed);
+    let type_changed = paths(TreeChange::TypeChanged);
+    let equal = diffs.is_empty();
+    let entries: Vec<Value> = diffs
+        .iter()
+        .map(|diff| {
+            let patch = diff.texts.as_ref().map(|(before, after)| {
+                unified_patch(&diff.path, before.as_deref(), after.as_deref())
+            });
+            Value::map()
+                .with("path", diff.path.as_str())
+                .with("status", diff.entry.change.name())
+                .with("leftType", diff.entry.left.map(EntryKind::name))
+                .with("rightType", diff.entry.right.map(EntryKind::name))
+                .with("binary", diff.binary)
+                .with("diff", patch)
+        })
+        .collect();
+    Ok(Value::map()
+        .with("equal", equal)
+        .with("added", added)
+        .with("removed", removed)
+        .with("changed", changed)
+        .with("typeChanged", type_changed)
+        .with("entries", Value::List(entries)))
+}
+
+/// One line per difference, then with `content` a pretty diff per text
+/// file, in the same colours as `pretty_diff_text`.
+pub fn pretty_dirs(
+    left: &Path,
+    right: &Path,
+    exclude: &str,
+    content: bool,
+) -> Result<String, String> {
+    let diffs = compare_dirs(left, right, exclude, content)?;
+    let count = |changes: &[TreeChange]| {
+        diffs
+            .iter()
+            .filter(|diff| changes.contains(&diff.entry.change))
+            .count()
+    };
+    let mut out = String::new();
+    let (left_name, right_name) = (left.display(), right.display());
+    if diffs.is_empty() {
+        let _ = writeln!(
+            out,
+            "• Compared {left_name} → {right_name} (\x1b[32mno differences\x1b[0m)"
+        );
+        return Ok(out);
+    }
+    let _ = writeln!(
+        out,
+        "• Compared {left_name} → {right_name} (\x1b[32m+{}\x1b[0m \x1b[31m-{}\x1b[0m \x1b[33m~{}\x1b[0m)",
+        count(&[TreeChange::Added]),
+        count(&[TreeChange::Removed]),
+        count(&[TreeChange::Changed, TreeChange::TypeChanged]),
+    );
+    for diff in &diffs {
+        let entry = &diff.entry;
+        let shown = |kind: Option<EntryKind>| match kind {
+            Some(EntryKind::Dir) => format!("{}/", diff.path),
+            _ => diff.path.clone(),
+        };
+        let _ = match entry.change {
+            TreeChange::Added => writeln!(out, "  \x1b[32m+ {}\x1b[0m", shown(entry.right)),
+            TreeChange::Removed => writeln!(out, "  \x1b[31m- {}\x1b[0m", shown(entry.left)),
+            TreeChange::Changed => writeln!(out, "  \x1b[33m~ {}\x1b[0m", diff.path),
+            TreeChange::TypeChanged => writeln!(
+                out,
+                "  \x1b[33m~ {}\x1b[0m \x1b[90m({} → {})\x1b[0m",
+                diff.path,
+                entry.left.map_or("", EntryKind::name),
+                entry.right.map_or("", EntryKind::name),
+            ),
+        };
+        if diff.binary {
+            let read = |root: &Path, kind: Option<EntryKind>| {
+                (kind == Some(EntryKind::File))
+                    .then(|| fs::read(root.join(&entry.rel)).unwrap_or_default())
+            };
+            let before = read(left, entry.left);
+            let after = read(right, entry.right);
+            let detail = binary_detail(before.as_deref(), after.as_deref());
+            let _ = writeln!(out, "    \x1b[90mbinary file {detail}\x1b[0m");
+        }
+    }
+    for diff in &diffs {
+        if let Some((before, after)) = &diff.texts {
+            let before = before.as_deref().unwrap_or("");
+            let after = after.as_deref().unwrap_or("");
+            out.push_str(&pretty_diff_text(
+                &diff.path,
+                before,
+                after,
+                &PrettyOptions::default(),
+            ));
+        }
+    }
+    Ok(out)
+}
@@ -1,3 +1,4 @@
+use crate::stdlib::process::dry_run_skip;
 use ruwren::foreign_v2::WrenString;
 use ruwren::{wren_impl, ModuleLibrary, WrenObject};
 use std::env;
@@ -15,6 +16,9 @@ impl Env {
     fn set(&self, key: WrenString, value: WrenString) {
         let key = key.into_string().unwrap_or_default();
         let value = value.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("set env {key}")) {
+            return;
+        }
         unsafe {
             env::set_var(&key, &value);
         }
@@ -23,6 +27,9 @@ impl Env {
 
     fn remove(&self, key: WrenString) {
         let key = key.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("unset env {key}")) {
+            return;
+        }
         unsafe {
             env::remove_var(&key);
         }
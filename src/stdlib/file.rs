@@ -1,19 +1,30 @@
 #![allow(non_snake_case)]
 
 use diffy::{Patch, PatchFormatter, apply, create_patch};
-use notify::event::ModifyKind;
+use notify::event::{
+    AccessKind, AccessMode, CreateKind, DataChange, MetadataKind, ModifyKind, RemoveKind,
+    RenameMode,
+};
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use ruwren::foreign_v2::WrenString;
-use ruwren::{wren_impl, ModuleLibrary, WrenObject};
-use similar::{Algorithm, ChangeTag, TextDiff};
+use crate::stdlib::bytes::{Bytes, BytesInstance};
+use crate::stdlib::diff::{
+    self, Format, Layout, Overflow, PrettyOptions, pretty_diff_text, pretty_path,
+};
+use crate::stdlib::merge;
+use crate::stdlib::patch;
+use crate::stdlib::process::{dry_run_skip, is_dry_run};
+use crate::stdlib::term;
+use crate::stdlib::tree::{self, TreeOptions};
+use crate::stdlib::value::Value;
+use ruwren::foreign_v2::{WrenString, WrenTo, WrenValue};
+use ruwren::{ModuleLibrary, SlotId, VM, WrenObject, wren_impl};
 use std::collections::{HashMap, VecDeque};
-use std::fmt::Write;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::mpsc::{Receiver, TryRecvError, channel};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
 use std::sync::{Mutex, MutexGuard};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(WrenObject, Default)]
 pub struct File;
@@ -25,22 +36,42 @@ impl File {
         fs::read_to_string(&path).unwrap_or_default()
     }
 
-    fn readBytes(&self, path: WrenString) -> Vec<f64> {
+    fn readBytes(&self, path: WrenString) -> Bytes {
         let path = path.into_string().unwrap_or_default();
-        fs::read(&path)
-            .map(|bytes| bytes.into_iter().map(|b| b as f64).collect())
-            .unwrap_or_default()
+        Bytes::from_vec(fs::read(&path).unwrap_or_default())
     }
 
     fn write(&self, path: WrenString, content: WrenString) -> bool {
         let path = path.into_string().unwrap_or_default();
         let content = content.into_string().unwrap_or_default();
+        if dry_run_write(&path, content.as_bytes(), false) {
+            return true;
+        }
         fs::write(&path, content).is_ok()
     }
 
-    fn writeBytes(&self, path: WrenString, bytes: Vec<f64>) -> bool {
+    /// Write via a sibling temp file + fsync + rename, so readers (and a
+    /// crash mid-write) only ever see the old or the new content.
+    fn writeAtomic(&self, path: WrenString, content: WrenString) -> bool {
+        let path = path.into_string().unwrap_or_default();
+        let content = content.into_string().unwrap_or_default();
+        if dry_run_write(&path, content.as_bytes(), false) {
+            return true;
+        }
+        write_atomic(Path::new(&path), content.as_bytes()).is_ok()
+    }
+
+    /// `bytes` is converted with `Bytes.from` on the Wren side.
+    #[wren_impl(object(bytes))]
+    fn writeBytes_(&self, path: WrenString, bytes: Option<Bytes>) -> bool {
         let path = path.into_string().unwrap_or_default();
-        let bytes: Vec<u8> = bytes.into_iter().map(|b| b as u8).collect();
+        let Some(bytes) = bytes else {
+            return false;
+        };
+        let bytes = bytes.into_vec();
+        if dry_run_write(&path, &bytes, false) {
+            return true;
+        }
         fs::write(&path, bytes).is_ok()
     }
 
@@ -50,6 +81,9 @@ impl File {
 
         let path = path.into_string().unwrap_or_default();
         let content = content.into_string().unwrap_or_default();
+        if dry_run_write(&path, content.as_bytes(), true) {
+            return true;
+        }
         OpenOptions::new()
             .create(true)
             .append(true)
@@ -75,6 +109,9 @@ impl File {
 
     fn delete(&self, path: WrenString) -> bool {
         let path = path.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("delete {path}")) {
+            return true;
+        }
         let p = Path::new(&path);
         if p.is_dir() {
             fs::remove_dir_all(&path).is_ok()
@@ -86,17 +123,33 @@ impl File {
     fn copy(&self, from: WrenString, to: WrenString) -> bool {
         let from = from.into_string().unwrap_or_default();
         let to = to.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("copy {from} -> {to}")) {
+            return true;
+        }
         fs::copy(&from, &to).is_ok()
     }
 
     fn rename(&self, from: WrenString, to: WrenString) -> bool {
         let from = from.into_string().unwrap_or_default();
         let to = to.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("rename {from} -> {to}")) {
+            return true;
+        }
         fs::rename(&from, &to).is_ok()
     }
 
+    /// Like `rename`, but also works across file systems (copy + delete).
+    fn movePath(&self, from: WrenString, to: WrenString) -> bool {
+        let from = from.into_string().unwrap_or_default();
+        let to = to.into_string().unwrap_or_default();
+        tree::move_path(Path::new(&from), Path::new(&to)).is_ok()
+    }
+
     fn mkdir(&self, path: WrenString) -> bool {
         let path = path.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("mkdir {path}")) {
+            return true;
+        }
         fs::create_dir_all(&path).is_ok()
     }
 
@@ -114,6 +167,392 @@ impl File {
             .map(|d| d.as_secs_f64())
             .unwrap_or(-1.0)
     }
+
+    /// Metadata map for `path` (following symlinks, except for `isSymlink`),
+    /// or `null` when the path does not exist.
+    fn stat(&self, path: WrenString) -> Option<Value> {
+        let path = path.into_string().unwrap_or_default();
+        let link = fs::symlink_metadata(&path).ok()?;
+        let meta = fs::metadata(&path).unwrap_or_else(|_| link.clone());
+        Some(stat_value(&meta, link.file_type().is_symlink()))
+    }
+
+    /// Set permission bits. `mode` is a Num (`493`) or an octal String (`"755"`).
+    fn chmod(&self, path: WrenString, mode: WrenValue) -> bool {
+        let path = path.into_string().unwrap_or_default();
+        let mode = match mode {
+            WrenValue::Number(n) if n >= 0.0 => n as u32,
+            WrenValue::String(text) => {
+                let text = String::from_utf8_lossy(&text);
+                match u32::from_str_radix(text.trim().trim_start_matches("0o"), 8) {
+                    Ok(mode) => mode,
+                    Err(_) => return false,
+                }
+            }
+            _ => return false,
+        };
+        if dry_run_skip(format_args!("chmod {mode:o} {path}")) {
+            return true;
+        }
+        set_mode(Path::new(&path), mode)
+    }
+
+    /// Add (or remove) execute bits for everyone who can read the file.
+    fn setExecutable(&self, path: WrenString, executable: bool) -> bool {
+        let path = path.into_string().unwrap_or_default();
+        let Some(mode) = file_mode(Path::new(&path)) else {
+            return false;
+        };
+        let mode = if executable {
+            mode | ((mode & 0o444) >> 2)
+        } else {
+            mode & !0o111
+        };
+        if dry_run_skip(format_args!("chmod {mode:o} {path}")) {
+            return true;
+        }
+        set_mode(Path::new(&path), mode)
+    }
+
+    /// Create `path` if missing and set its access/modified times to now.
+    fn touch(&self, path: WrenString) -> bool {
+        let path = path.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("touch {path}")) {
+            return true;
+        }
+        if !Path::new(&path).exists()
+            && fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .is_err()
+        {
+            return false;
+        }
+        set_times(Path::new(&path), SystemTime::now(), true)
+    }
+
+    /// Set the last-modified time (Unix seconds).
+    fn setModified(&self, path: WrenString, seconds: f64) -> bool {
+        let path = path.into_string().unwrap_or_default();
+        if !seconds.is_finite() || seconds < 0.0 {
+            return false;
+        }
+        if dry_run_skip(format_args!("set modified {path} = {seconds}")) {
+            return true;
+        }
+        let time = UNIX_EPOCH + Duration::from_secs_f64(seconds);
+        set_times(Path::new(&path), time, false)
+    }
+
+    /// Create `link` pointing at `target` (which need not exist).
+    fn symlink(&self, target: WrenString, link: WrenString) -> bool {
+        let target = target.into_string().unwrap_or_default();
+        let link = link.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("symlink {link} -> {target}")) {
+            return true;
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&target, &link).is_ok()
+        }
+        #[cfg(windows)]
+        {
+            if Path::new(&target).is_dir() {
+                std::os::windows::fs::symlink_dir(&target, &link).is_ok()
+            } else {
+                std::os::windows::fs::symlink_file(&target, &link).is_ok()
+            }
+        }
+    }
+
+    /// Target of a symlink, or `null` if `path` is not a symlink.
+    fn readLink(&self, path: WrenString) -> Option<String> {
+        let path = path.into_string().unwrap_or_default();
+        fs::read_link(&path)
+            .ok()
+            .map(|p| p.to_string_lossy().to_string())
+    }
+
+    fn hardLink(&self, from: WrenString, to: WrenString) -> bool {
+        let from = from.into_string().unwrap_or_default();
+        let to = to.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("hard link {to} -> {from}")) {
+            return true;
+        }
+        fs::hard_link(&from, &to).is_ok()
+    }
+}
+
+/// Dry-run stand-in for a write: print what `path` would become (as a diff
+/// against the current content when both sides are text) and return `true`.
+fn dry_run_write(path: &str, bytes: &[u8], append: bool) -> bool {
+    if !is_dry_run() {
+        return false;
+    }
+    let existing = fs::read(path).ok();
+    let verb = match (&existing, append) {
+        (None, _) => "create",
+        (Some(_), true) => "append",
+        (Some(_), false) => "write",
+    };
+    println!("[dry-run] {verb} {path} ({} bytes)", bytes.len());
+
+    let before = existing.unwrap_or_default();
+    let after = if append {
+        [before.as_slice(), bytes].concat()
+    } else {
+        bytes.to_vec()
+    };
+    if let (Ok(before), Ok(after)) = (std::str::from_utf8(&before), std::str::from_utf8(&after))
+        && before != after
+    {
+        let options = PrettyOptions {
+            max_width: term::terminal_width(),
+            ..PrettyOptions::default()
+        };
+        print!("{}", term::paint(&pretty_diff_text(path, before, after, &options)));
+    } else if before != after {
+        print!("{}", term::paint(&diff::pretty_binary(path, &before, &after)));
+    }
+    true
+}
+
+fn unix_seconds(time: std::io::Result<SystemTime>) -> Option<f64> {
+    time.ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs_f64())
+}
+
+fn stat_value(meta: &fs::Metadata, is_symlink: bool) -> Value {
+    let stat = Value::map()
+        .with("size", meta.len())
+        .with("isFile", meta.is_file())
+        .with("isDirectory", meta.is_dir())
+        .with("isSymlink", is_symlink)
+        .with("readonly", meta.permissions().readonly())
+        .with("modified", unix_seconds(meta.modified()))
+        .with("accessed", unix_seconds(meta.accessed()))
+        .with("created", unix_seconds(meta.created()));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let mode = meta.mode() & 0o7777;
+        stat.with("mode", mode)
+            .with("modeString", format!("{:o}", mode))
+            .with("uid", meta.uid())
+            .with("gid", meta.gid())
+            .with("inode", meta.ino())
+            .with("nlink", meta.nlink())
+            .with("device", meta.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        stat.with("mode", Value::Null)
+            .with("modeString", Value::Null)
+            .with("uid", Value::Null)
+            .with("gid", Value::Null)
+            .with("inode", Value::Null)
+            .with("nlink", Value::Null)
+            .with("device", Value::Null)
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).ok().map(|m| m.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(path: &Path) -> Option<u32> {
+    fs::metadata(path)
+        .ok()
+        .map(|m| if m.permissions().readonly() { 0o444 } else { 0o666 })
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777)).is_ok()
+}
+
+/// Only the owner-write bit maps onto Windows (the read-only attribute).
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> bool {
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+    let mut permissions = meta.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions).is_ok()
+}
+
+fn set_times(path: &Path, time: SystemTime, include_accessed: bool) -> bool {
+    // Setting times needs a handle; prefer a writable one (required on
+    // Windows) and fall back to read-only for directories.
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .or_else(|_| fs::File::open(path));
+    let Ok(file) = file else {
+        return false;
+    };
+    let mut times = fs::FileTimes::new().set_modified(time);
+    if include_accessed {
+        times = times.set_accessed(time);
+    }
+    file.set_times(times).is_ok()
+}
+
+/// Absolute form of `path` without touching the file system: joined onto the
+/// current directory with `.` and `..` resolved lexically.
+fn lexical_absolute(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+    let mut out = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(1);
+static TEMP_PATHS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// A name that will not collide with other wrun processes or earlier calls.
+pub(crate) fn unique_name(prefix: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    format!(
+        "{}{}-{}-{:08x}",
+        prefix,
+        std::process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::SeqCst),
+        nanos
+    )
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = parent.join(unique_name(&format!(".{file_name}.tmp-")));
+
+    let result = (|| {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)?;
+        file.write_all(bytes)?;
+        if let Ok(existing) = fs::metadata(path) {
+            file.set_permissions(existing.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        // Persist the rename itself; not every platform can open a directory.
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Delete every temp path that was not kept. Runs when the VM finishes and
+/// from `Process.exit`.
+pub fn cleanup_temp_paths() {
+    let paths = std::mem::take(&mut *TEMP_PATHS.lock().unwrap());
+    for path in paths.into_iter().rev() {
+        if path.is_dir() {
+            let _ = fs::remove_dir_all(&path);
+        } else {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+#[derive(WrenObject, Default)]
+pub struct TempUtil;
+
+#[wren_impl]
+impl TempUtil {
+    /// Create a temp file (or directory) under the system temp dir and
+    /// register it for cleanup. Returns "" on failure.
+    fn create(&self, prefix: WrenString, directory: bool) -> String {
+        let prefix = prefix.into_string().unwrap_or_default();
+        let base = std::env::temp_dir();
+
+        for _ in 0..16 {
+            let path = base.join(unique_name(&prefix));
+            let created = if directory {
+                fs::create_dir(&path)
+            } else {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .map(|_| ())
+            };
+            match created {
+                Ok(()) => {
+                    TEMP_PATHS.lock().unwrap().push(path.clone());
+                    return path.to_string_lossy().to_string();
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(_) => break,
+            }
+        }
+        String::new()
+    }
+
+    /// Stop tracking `path` so it survives VM exit.
+    fn keep(&self, path: WrenString) -> bool {
+        let path = PathBuf::from(path.into_string().unwrap_or_default());
+        let mut paths = TEMP_PATHS.lock().unwrap();
+        let before = paths.len();
+        paths.retain(|p| p != &path);
+        paths.len() != before
+    }
+
+    /// Delete `path` now and stop tracking it.
+    fn remove(&self, path: WrenString) -> bool {
+        let path = PathBuf::from(path.into_string().unwrap_or_default());
+        TEMP_PATHS.lock().unwrap().retain(|p| p != &path);
+        if path.is_dir() {
+            fs::remove_dir_all(&path).is_ok()
+        } else {
+            fs::remove_file(&path).is_ok()
+        }
+    }
 }
 
 #[derive(WrenObject, Default)]
@@ -135,11 +574,17 @@ impl Dir {
 
     fn create(&self, path: WrenString) -> bool {
         let path = path.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("mkdir {path}")) {
+            return true;
+        }
         fs::create_dir_all(&path).is_ok()
     }
 
     fn remove(&self, path: WrenString) -> bool {
         let path = path.into_string().unwrap_or_default();
+        if dry_run_skip(format_args!("delete {path}")) {
+            return true;
+        }
         fs::remove_dir_all(&path).is_ok()
     }
 
@@ -147,6 +592,52 @@ impl Dir {
         let path = path.into_string().unwrap_or_default();
         Path::new(&path).is_dir()
     }
+
+    /// Recursive copy of the contents of `src` into `dst`. `exclude` is a
+    /// `\x00`-joined glob list (unpacked from the options map in Wren).
+    fn copyTree(
+        &self,
+        src: WrenString,
+        dst: WrenString,
+        overwrite: bool,
+        preserve_times: bool,
+        exclude: WrenString,
+    ) -> Result<bool, String> {
+        let src = src.into_string().unwrap_or_default();
+        let dst = dst.into_string().unwrap_or_default();
+        let exclude = exclude.into_string().unwrap_or_default();
+        let options = TreeOptions {
+            overwrite,
+            preserve_times,
+            exclude: tree::build_excludes(&exclude).map_err(|err| format!("Dir: {err}"))?,
+            ..TreeOptions::default()
+        };
+        Ok(tree::copy_tree(Path::new(&src), Path::new(&dst), &options)
+            .errors
+            .is_empty())
+    }
+
+    /// One-way sync of `dst` to match `src`; returns a summary map.
+    fn syncTree(
+        &self,
+        src: WrenString,
+        dst: WrenString,
+        delete: bool,
+        checksum: bool,
+        exclude: WrenString,
+    ) -> Result<Value, String> {
+        let src = src.into_string().unwrap_or_default();
+        let dst = dst.into_string().unwrap_or_default();
+        let exclude = exclude.into_string().unwrap_or_default();
+        let options = TreeOptions {
+            overwrite: true,
+            preserve_times: true,
+            delete,
+            checksum,
+            exclude: tree::build_excludes(&exclude).map_err(|err| format!("Dir: {err}"))?,
+        };
+        Ok(tree::sync_tree(Path::new(&src), Path::new(&dst), &options).into_value())
+    }
 }
 
 #[derive(WrenObject, Default)]
@@ -184,11 +675,21 @@ impl PathUtil {
             .unwrap_or_default()
     }
 
+    /// Lexically absolute path; works for paths that do not exist and never
+    /// resolves symlinks.
     fn absolute(&self, path: WrenString) -> String {
         let path = path.into_string().unwrap_or_default();
-        std::fs::canonicalize(&path)
+        lexical_absolute(Path::new(&path))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Fully resolved path (symlinks followed), or `null` if it does not exist.
+    fn canonical(&self, path: WrenString) -> Option<String> {
+        let path = path.into_string().unwrap_or_default();
+        fs::canonicalize(&path)
+            .ok()
             .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or(path)
     }
 
     fn isAbsolute(&self, path: WrenString) -> bool {
@@ -203,14 +704,166 @@ static NATIVE_WATCHERS: Mutex<Option<HashMap<u64, NativeWatcherState>>> = Mutex:
 #[derive(Default)]
 struct NativeEvent {
     kind: String,
+    sub_kind: Option<String>,
     timestamp: f64,
     paths: Vec<String>,
+    is_directory: bool,
+    tracker: Option<usize>,
+    error: Option<String>,
+}
+
+/// Native events cross into Wren as maps:
+/// `{kind, subKind, timestamp, paths, isDirectory, tracker, error}`.
+impl WrenTo for NativeEvent {
+    const SCRATCH_SPACE: usize = 3;
+
+    fn to_vm(self, vm: &VM, slot: SlotId, scratch_start: SlotId) {
+        let key = scratch_start;
+        let value = scratch_start + 1;
+        let item = scratch_start + 2;
+        let put = |name: &str| {
+            vm.set_slot_string(key, name);
+            vm.set_map_value(slot, key, value);
+        };
+        let set_optional = |text: Option<String>| match text {
+            Some(text) => vm.set_slot_string(value, text),
+            None => vm.set_slot_null(value),
+        };
+
+        vm.set_slot_new_map(slot);
+
+        vm.set_slot_string(value, &self.kind);
+        put("kind");
+        set_optional(self.sub_kind);
+        put("subKind");
+        vm.set_slot_double(value, self.timestamp);
+        put("timestamp");
+        vm.set_slot_bool(value, self.is_directory);
+        put("isDirectory");
+        match self.tracker {
+            Some(tracker) => vm.set_slot_double(value, tracker as f64),
+            None => vm.set_slot_null(value),
+        }
+        put("tracker");
+        set_optional(self.error);
+        put("error");
+
+        vm.set_slot_new_list(value);
+        for (idx, path) in self.paths.into_iter().enumerate() {
+            vm.set_slot_string(item, path);
+            vm.insert_in_list(value, idx as i32, item);
+        }
+        put("paths");
+    }
 }
 
 struct NativeWatcherState {
     _watcher: RecommendedWatcher,
     rx: Receiver<notify::Result<Event>>,
     queue: VecDeque<NativeEvent>,
+    debounce: Option<Duration>,
+    coalescer: EventCoalescer,
+    batches: VecDeque<Vec<NativeEvent>>,
+}
+
+/// Collects raw events per path while a debounce window is open, merging
+/// bursts (create+modify, modify+delete, ...) into one event per path.
+#[derive(Default)]
+struct EventCoalescer {
+    order: Vec<String>,
+    pending: HashMap<String, NativeEvent>,
+    last_event_at: Option<Instant>,
+    /// Counts errors so each gets its own key.
+    errors: u64,
+}
+
+impl EventCoalescer {
+    fn push(&mut self, event: NativeEvent) {
+        self.last_event_at = Some(Instant::now());
+
+        // Errors carry a message instead of paths, so never merge them.
+        let key = if event.kind == "error" {
+            self.errors += 1;
+            format!("\x00error{}", self.errors)
+        } else {
+            event.paths.join("\x00")
+        };
+
+        match self.pending.remove(&key) {
+            Some(previous) => match merge_event_kinds(&previous.kind, &event.kind) {
+                Some(kind) => {
+                    // Keep the details (sub-kind, tracker) of whichever event
+                    // the merged kind came from.
+                    let merged = if kind == event.kind {
+                        NativeEvent {
+                            timestamp: event.timestamp,
+                            ..event
+                        }
+                    } else if kind == previous.kind {
+                        NativeEvent {
+                            timestamp: event.timestamp,
+                            is_directory: event.is_directory,
+                            ..previous
+                        }
+                    } else {
+                        NativeEvent {
+                            kind: kind.to_string(),
+                            sub_kind: None,
+                            tracker: None,
+                            ..event
+                        }
+                    };
+                    self.pending.insert(key, merged);
+                }
+                None => self.order.retain(|k| k != &key),
+            },
+            None => {
+                self.order.push(key.clone());
+                self.pending.insert(key, event);
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Time left until the window closes, or `None` when nothing is pending.
+    fn remaining(&self, window: Duration) -> Option<Duration> {
+        if self.is_empty() {
+            return None;
+        }
+        let elapsed = self.last_event_at.map(|t| t.elapsed()).unwrap_or(window);
+        Some(window.saturating_sub(elapsed))
+    }
+
+    fn take(&mut self) -> Vec<NativeEvent> {
+        let mut pending = std::mem::take(&mut self.pending);
+        self.last_event_at = None;
+        std::mem::take(&mut self.order)
+            .into_iter()
+            .filter_map(|key| pending.remove(&key))
+            .collect()
+    }
+}
+
+/// Merge two kinds observed for the same path inside one debounce window.
+/// Returns `None` when the pair cancels out (created, then deleted).
+fn merge_event_kinds(previous: &str, next: &str) -> Option<&'static str> {
+    let kind = match (previous, next) {
+        ("created", "deleted") => return None,
+        ("created", _) => "created",
+        ("deleted", "created") | ("deleted", "modified") => "modified",
+        (_, "deleted") => "deleted",
+        ("modified", "accessed") | ("modified", "other") => "modified",
+        ("renamed", "accessed") | ("renamed", "other") => "renamed",
+        (_, "created") => "created",
+        (_, "modified") => "modified",
+        (_, "renamed") => "renamed",
+        (_, "accessed") => "accessed",
+        _ => "other",
+    };
+    Some(kind)
 }
 
 fn get_native_watchers() -> MutexGuard<'static, Option<HashMap<u64, NativeWatcherState>>> {
@@ -239,10 +892,73 @@ fn kind_to_string(kind: &EventKind) -> String {
     }
 }
 
+fn access_mode_name(prefix: &str, mode: &AccessMode) -> String {
+    let mode = match mode {
+        AccessMode::Any => return prefix.to_string(),
+        AccessMode::Execute => "execute",
+        AccessMode::Read => "read",
+        AccessMode::Write => "write",
+        AccessMode::Other => "other",
+    };
+    format!("{prefix}-{mode}")
+}
+
+/// The finer-grained `notify` detail behind a kind, e.g. `"folder"` for a
+/// created directory or `"rename-from"` / `"rename-to"` for the halves of a
+/// rename. `None` when the backend did not say.
+fn sub_kind_to_string(kind: &EventKind) -> Option<String> {
+    let name = match kind {
+        EventKind::Create(CreateKind::File) | EventKind::Remove(RemoveKind::File) => "file",
+        EventKind::Create(CreateKind::Folder) | EventKind::Remove(RemoveKind::Folder) => {
+            "folder"
+        }
+        EventKind::Create(CreateKind::Other) | EventKind::Remove(RemoveKind::Other) => "other",
+        EventKind::Modify(ModifyKind::Data(change)) => match change {
+            DataChange::Any => "data",
+            DataChange::Size => "data-size",
+            DataChange::Content => "data-content",
+            DataChange::Other => "data-other",
+        },
+        EventKind::Modify(ModifyKind::Metadata(metadata)) => match metadata {
+            MetadataKind::Any => "metadata",
+            MetadataKind::AccessTime => "metadata-access-time",
+            MetadataKind::WriteTime => "metadata-write-time",
+            MetadataKind::Permissions => "metadata-permissions",
+            MetadataKind::Ownership => "metadata-ownership",
+            MetadataKind::Extended => "metadata-extended",
+            MetadataKind::Other => "metadata-other",
+        },
+        EventKind::Modify(ModifyKind::Name(mode)) => match mode {
+            RenameMode::Any => "rename",
+            RenameMode::To => "rename-to",
+            RenameMode::From => "rename-from",
+            RenameMode::Both => "rename-both",
+            RenameMode::Other => "rename-other",
+        },
+        EventKind::Modify(ModifyKind::Other) => "other",
+        EventKind::Access(AccessKind::Read) => "read",
+        EventKind::Access(AccessKind::Open(mode)) => return Some(access_mode_name("open", mode)),
+        EventKind::Access(AccessKind::Close(mode)) => {
+            return Some(access_mode_name("close", mode));
+        }
+        EventKind::Access(AccessKind::Other) => "other",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
 fn normalize_event(event: Event) -> NativeEvent {
+    let is_directory = match event.kind {
+        EventKind::Create(CreateKind::Folder) | EventKind::Remove(RemoveKind::Folder) => true,
+        _ => event.paths.last().is_some_and(|p| p.is_dir()),
+    };
     NativeEvent {
         kind: kind_to_string(&event.kind),
+        sub_kind: sub_kind_to_string(&event.kind),
         timestamp: now_unix_seconds(),
+        is_directory,
+        tracker: event.tracker(),
+        error: None,
         paths: event
             .paths
             .into_iter()
@@ -251,201 +967,157 @@ fn normalize_event(event: Event) -> NativeEvent {
     }
 }
 
-fn drain_native_events(state: &mut NativeWatcherState) {
-    loop {
-        match state.rx.try_recv() {
-            Ok(result) => {
-                match result {
-                    Ok(event) => state.queue.push_back(normalize_event(event)),
-                    Err(err) => state.queue.push_back(NativeEvent {
-                        kind: "error".to_string(),
-                        timestamp: now_unix_seconds(),
-                        paths: vec![err.to_string()],
-                    }),
-                }
-            }
-            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
-        }
+fn error_event(err: notify::Error) -> NativeEvent {
+    NativeEvent {
+        kind: "error".to_string(),
+        timestamp: now_unix_seconds(),
+        error: Some(err.to_string()),
+        paths: err
+            .paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect(),
+        ..NativeEvent::default()
     }
 }
 
-fn encode_native_event(event: NativeEvent) -> Vec<String> {
-    let mut parts = Vec::with_capacity(2 + event.paths.len());
-    parts.push(event.kind);
-    parts.push(format!("{:.6}", event.timestamp));
-    parts.extend(event.paths);
-    parts
-}
-
-fn pretty_path(path: &str) -> String {
-    if path.is_empty() {
-        "file".to_string()
+fn accept_native_result(state: &mut NativeWatcherState, result: notify::Result<Event>) {
+    let event = match result {
+        Ok(event) => normalize_event(event),
+        Err(err) => error_event(err),
+    };
+    if state.debounce.is_some() {
+        state.coalescer.push(event);
     } else {
-        path.to_string()
+        state.queue.push_back(event);
     }
 }
 
-fn with_path_headers(patch: String, path: &str) -> String {
-    let path = pretty_path(path);
-    let old_header = format!("--- a/{path}");
-    let new_header = format!("+++ b/{path}");
-    patch
-        .replacen("--- original", &old_header, 1)
-        .replacen("+++ modified", &new_header, 1)
-}
-
-fn granularity_name(name: &str) -> &str {
-    match name.to_ascii_lowercase().as_str() {
-        "word" => "word",
-        "char" => "char",
-        _ => "line",
+/// Move the coalesced events into a ready batch once the debounce window
+/// has been quiet for its full duration.
+fn flush_ready_batch(state: &mut NativeWatcherState) {
+    let Some(window) = state.debounce else {
+        return;
+    };
+    if state.coalescer.remaining(window) == Some(Duration::ZERO) {
+        let batch = state.coalescer.take();
+        if !batch.is_empty() {
+            state.batches.push_back(batch);
+        }
     }
 }
 
-fn algorithm_name(name: &str) -> &str {
-    match name.to_ascii_lowercase().as_str() {
-        "patience" => "patience",
-        "lcs" => "lcs",
-        _ => "myers",
+fn drain_native_events(state: &mut NativeWatcherState) {
+    while let Ok(result) = state.rx.try_recv() {
+        accept_native_result(state, result);
     }
+    flush_ready_batch(state);
 }
 
-fn make_diff<'a>(
-    before: &'a str,
-    after: &'a str,
-    granularity: &str,
-    algorithm: &str,
-) -> TextDiff<'a, 'a, 'a, str> {
-    let mut cfg = TextDiff::configure();
-    let algorithm = match algorithm_name(algorithm) {
-        "patience" => Algorithm::Patience,
-        "lcs" => Algorithm::Lcs,
-        _ => Algorithm::Myers,
+fn pop_native_event(state: &mut NativeWatcherState) -> Option<NativeEvent> {
+    if let Some(event) = state.queue.pop_front() {
+        return Some(event);
+    }
+    let batch = state.batches.front_mut()?;
+    let event = if batch.is_empty() {
+        None
+    } else {
+        Some(batch.remove(0))
     };
-    cfg.algorithm(algorithm);
-    match granularity_name(granularity) {
-        "word" => cfg.diff_words(before, after),
-        "char" => cfg.diff_chars(before, after),
-        _ => cfg.diff_lines(before, after),
+    if batch.is_empty() {
+        state.batches.pop_front();
     }
+    event
 }
 
-fn strip_newline(value: &str) -> &str {
-    value.strip_suffix('\n').unwrap_or(value)
+fn ready_event_count(state: &NativeWatcherState) -> usize {
+    state.queue.len() + state.batches.iter().map(|b| b.len()).sum::<usize>()
 }
 
-fn format_line_number(idx: Option<usize>, width: usize) -> String {
-    match idx {
-        Some(v) => format!("{:>width$}", v + 1, width = width),
-        None => " ".repeat(width),
-    }
-}
-
-fn pretty_diff_text(path: &str, before: &str, after: &str, granularity: &str, algorithm: &str) -> String {
-    let path = pretty_path(path);
-    let diff = make_diff(before, after, granularity, algorithm);
-
-    let mut added = 0usize;
-    let mut removed = 0usize;
-    let mut max_old = 1usize;
-    let mut max_new = 1usize;
-    for change in diff.iter_all_changes() {
-        if let Some(old_idx) = change.old_index() {
-            max_old = max_old.max(old_idx + 1);
-        }
-        if let Some(new_idx) = change.new_index() {
-            max_new = max_new.max(new_idx + 1);
-        }
-        match change.tag() {
-            ChangeTag::Insert => added += 1,
-            ChangeTag::Delete => removed += 1,
-            ChangeTag::Equal => {}
-        }
-    }
-
-    let mut out = String::new();
-    let mode = granularity_name(granularity);
-    let algo = algorithm_name(algorithm);
-    let _ = writeln!(
-        &mut out,
-        "• Edited {path} (\x1b[32m+{added}\x1b[0m \x1b[31m-{removed}\x1b[0m) [{mode}/{algo}]"
-    );
-
-    if mode == "line" {
-        let line_number_width = max_old.max(max_new).to_string().len();
-        let elision_indent = 2 + line_number_width + 1 + line_number_width + 1;
-        let groups = diff.grouped_ops(2);
-        for (idx, group) in groups.iter().enumerate() {
-            if idx > 0 {
-                let _ = writeln!(&mut out, "{}\x1b[90m⋮\x1b[0m", " ".repeat(elision_indent));
+/// Block until a debounced batch is ready or `timeout` elapses
+/// (`None` waits forever).
+fn wait_for_batch(state: &mut NativeWatcherState, timeout: Option<Duration>) {
+    let Some(window) = state.debounce else {
+        return;
+    };
+    let deadline = timeout.map(|t| Instant::now() + t);
+
+    drain_native_events(state);
+    while state.batches.is_empty() {
+        let until_deadline = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        let wait = match (state.coalescer.remaining(window), until_deadline) {
+            (Some(quiet), Some(limit)) => quiet.min(limit),
+            (Some(quiet), None) => quiet,
+            (None, Some(limit)) => limit,
+            (None, None) => {
+                match state.rx.recv() {
+                    Ok(result) => accept_native_result(state, result),
+                    Err(_) => break,
+                }
+                drain_native_events(state);
+                continue;
             }
-            for op in group {
-                for change in diff.iter_changes(op) {
-                    let (old_idx, new_idx) = match change.tag() {
-                        ChangeTag::Delete => (change.old_index(), None),
-                        ChangeTag::Insert => (None, change.new_index()),
-                        ChangeTag::Equal => (change.old_index(), change.new_index()),
-                    };
+        };
 
-                    let old_num = format_line_number(old_idx, line_number_width);
-                    let new_num = format_line_number(new_idx, line_number_width);
-                    let text = strip_newline(change.value());
-                    let _ = write!(
-                        &mut out,
-                        "  \x1b[90m{old_num}\x1b[0m \x1b[90m{new_num}\x1b[0m "
-                    );
-
-                    match change.tag() {
-                        ChangeTag::Delete => {
-                            if text.is_empty() {
-                                let _ = writeln!(&mut out, "\x1b[31m-\x1b[0m");
-                            } else {
-                                let _ = writeln!(&mut out, "\x1b[31m-{text}\x1b[0m");
-                            }
-                        }
-                        ChangeTag::Insert => {
-                            if text.is_empty() {
-                                let _ = writeln!(&mut out, "\x1b[32m+\x1b[0m");
-                            } else {
-                                let _ = writeln!(&mut out, "\x1b[32m+{text}\x1b[0m");
-                            }
-                        }
-                        ChangeTag::Equal => {
-                            if text.is_empty() {
-                                let _ = writeln!(&mut out, " ");
-                            } else {
-                                let _ = writeln!(&mut out, " {text}");
-                            }
-                        }
-                    }
-                }
+        if wait.is_zero() {
+            flush_ready_batch(state);
+            if until_deadline == Some(Duration::ZERO) {
+                break;
             }
+            continue;
         }
-    } else {
-        let _ = writeln!(
-            &mut out,
-            "    \x1b[90mGranularity: {mode} (inline token view)\x1b[0m"
-        );
-        let _ = write!(&mut out, "    ");
-        for change in diff.iter_all_changes() {
-            match change.tag() {
-                ChangeTag::Delete => {
-                    let _ = write!(&mut out, "\x1b[31m{}\x1b[0m", change.value());
-                }
-                ChangeTag::Insert => {
-                    let _ = write!(&mut out, "\x1b[32m{}\x1b[0m", change.value());
-                }
-                ChangeTag::Equal => {
-                    let _ = write!(&mut out, "{}", change.value());
-                }
+
+        match state.rx.recv_timeout(wait) {
+            Ok(result) => accept_native_result(state, result),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                flush_ready_batch(state);
+                break;
             }
         }
-        if !out.ends_with('\n') {
-            out.push('\n');
-        }
+        drain_native_events(state);
     }
+}
 
-    out
+fn with_path_headers(patch: String, path: &str) -> String {
+    let path = pretty_path(path);
+    let old_header = format!("--- a/{path}");
+    let new_header = format!("+++ b/{path}");
+    patch
+        .replacen("--- original", &old_header, 1)
+        .replacen("+++ modified", &new_header, 1)
+}
+
+/// Result of applying a patch, passed to Wren as
+/// `{"ok": Bool, "text": String|null, "error": String|null}`.
+struct PatchOutcome(Result<String, String>);
+
+impl WrenTo for PatchOutcome {
+    const SCRATCH_SPACE: usize = 2;
+
+    fn to_vm(self, vm: &VM, slot: SlotId, scratch_start: SlotId) {
+        let key = scratch_start;
+        let value = scratch_start + 1;
+        let put = |name: &str| {
+            vm.set_slot_string(key, name);
+            vm.set_map_value(slot, key, value);
+        };
+
+        vm.set_slot_new_map(slot);
+        vm.set_slot_bool(value, self.0.is_ok());
+        put("ok");
+        let (text, error) = match self.0 {
+            Ok(text) => (Some(text), None),
+            Err(error) => (None, Some(error)),
+        };
+        for (name, field) in [("text", text), ("error", error)] {
+            match field {
+                Some(field) => vm.set_slot_string(value, field),
+                None => vm.set_slot_null(value),
+            }
+            put(name);
+        }
+    }
 }
 
 #[derive(WrenObject, Default)]
@@ -462,6 +1134,9 @@ impl DiffUtil {
         algorithm: WrenString,
     ) -> String {
         let path = path.into_string().unwrap_or_default();
+        if diff::is_binary(before.bytes()) || diff::is_binary(after.bytes()) {
+            return diff::pretty_binary(&path, before.bytes(), after.bytes());
+        }
         let before = before.into_string().unwrap_or_default();
         let after = after.into_string().unwrap_or_default();
         let granularity = granularity
@@ -470,11 +1145,125 @@ impl DiffUtil {
         let algorithm = algorithm
             .into_string()
             .unwrap_or_else(|_| "myers".to_string());
-        pretty_diff_text(&path, &before, &after, &granularity, &algorithm)
+        let options = PrettyOptions {
+            granularity: &granularity,
+            algorithm: &algorithm,
+            ..PrettyOptions::default()
+        };
+        pretty_diff_text(&path, &before, &after, &options)
+    }
+
+    /// `pretty` with layout and width control. `max_width` of 0 means
+    /// unlimited and a negative one the terminal width.
+    #[allow(clippy::too_many_arguments)]
+    fn prettyWith_(
+        &self,
+        path: WrenString,
+        before: WrenString,
+        after: WrenString,
+        granularity: WrenString,
+        algorithm: WrenString,
+        layout: WrenString,
+        max_width: f64,
+        overflow: WrenString,
+        highlight: bool,
+    ) -> String {
+        let path = path.into_string().unwrap_or_default();
+        if diff::is_binary(before.bytes()) || diff::is_binary(after.bytes()) {
+            return diff::pretty_binary(&path, before.bytes(), after.bytes());
+        }
+        let before = before.into_string().unwrap_or_default();
+        let after = after.into_string().unwrap_or_default();
+        let granularity = granularity.into_string().unwrap_or_default();
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        let layout = layout.into_string().unwrap_or_default();
+        let overflow = overflow.into_string().unwrap_or_default();
+        let max_width = if max_width < 0.0 {
+            term::terminal_width()
+        } else if max_width.is_finite() && max_width >= 1.0 {
+            Some(max_width as usize)
+        } else {
+            None
+        };
+        let options = PrettyOptions {
+            granularity: &granularity,
+            algorithm: &algorithm,
+            layout: Layout::parse(&layout),
+            max_width,
+            overflow: Overflow::parse(&overflow),
+            highlight,
+        };
+        pretty_diff_text(&path, &before, &after, &options)
+    }
+
+    /// Compare two directory trees; `exclude` is `\x00`-joined globs.
+    fn dirs_(
+        &self,
+        left: WrenString,
+        right: WrenString,
+        exclude: WrenString,
+        content: bool,
+    ) -> Result<Value, String> {
+        let left = left.into_string().unwrap_or_default();
+        let right = right.into_string().unwrap_or_default();
+        let exclude = exclude.into_string().unwrap_or_default();
+        diff::dirs_value(Path::new(&left), Path::new(&right), &exclude, content)
+    }
+
+    fn prettyDirs_(
+        &self,
+        left: WrenString,
+        right: WrenString,
+        exclude: WrenString,
+        content: bool,
+    ) -> Result<String, String> {
+        let left = left.into_string().unwrap_or_default();
+        let right = right.into_string().unwrap_or_default();
+        let exclude = exclude.into_string().unwrap_or_default();
+        diff::pretty_dirs(Path::new(&left), Path::new(&right), &exclude, content)
+    }
+
+    /// Re-render ANSI pretty output as `plain` or `html`.
+    fn render_(&self, text: WrenString, format: WrenString) -> String {
+        let text = String::from_utf8_lossy(text.bytes()).into_owned();
+        let format = format.into_string().unwrap_or_default();
+        diff::render(&text, Format::parse(&format))
+    }
+
+    fn htmlStyle(&self) -> String {
+        diff::HTML_STYLE.to_string()
+    }
+
+    fn hunks_(
+        &self,
+        before: WrenString,
+        after: WrenString,
+        context: f64,
+        algorithm: WrenString,
+    ) -> Result<Value, String> {
+        if diff::is_binary(before.bytes()) || diff::is_binary(after.bytes()) {
+            return Err("Diff.hunks: cannot split binary content into hunks".to_string());
+        }
+        let before = before.into_string().unwrap_or_default();
+        let after = after.into_string().unwrap_or_default();
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        let context = if context.is_finite() && context > 0.0 {
+            context as usize
+        } else {
+            0
+        };
+        Ok(diff::hunks(&before, &after, context, &algorithm))
+    }
+
+    fn terminalWidth(&self) -> Option<f64> {
+        term::terminal_width().map(|width| width as f64)
     }
 
     fn patch(&self, path: WrenString, before: WrenString, after: WrenString) -> String {
         let path = path.into_string().unwrap_or_default();
+        if diff::is_binary(before.bytes()) || diff::is_binary(after.bytes()) {
+            return diff::binary_patch(&path, before.bytes(), after.bytes());
+        }
         let before = before.into_string().unwrap_or_default();
         let after = after.into_string().unwrap_or_default();
         with_path_headers(create_patch(&before, &after).to_string(), &path)
@@ -482,6 +1271,9 @@ impl DiffUtil {
 
     fn patchColor(&self, path: WrenString, before: WrenString, after: WrenString) -> String {
         let path = path.into_string().unwrap_or_default();
+        if diff::is_binary(before.bytes()) || diff::is_binary(after.bytes()) {
+            return diff::binary_patch(&path, before.bytes(), after.bytes());
+        }
         let before = before.into_string().unwrap_or_default();
         let after = after.into_string().unwrap_or_default();
         let patch = create_patch(&before, &after);
@@ -490,17 +1282,81 @@ impl DiffUtil {
         with_path_headers(rendered, &path)
     }
 
-    fn applyPatchResult(&self, base: WrenString, patch_text: WrenString) -> Vec<String> {
+    fn applyPatchResult(&self, base: WrenString, patch_text: WrenString) -> PatchOutcome {
         let base = base.into_string().unwrap_or_default();
         let patch_text = patch_text.into_string().unwrap_or_default();
 
-        match Patch::from_str(&patch_text) {
-            Ok(patch) => match apply(&base, &patch) {
-                Ok(result) => vec!["ok".to_string(), result],
-                Err(err) => vec!["error".to_string(), err.to_string()],
-            },
-            Err(err) => vec!["error".to_string(), err.to_string()],
-        }
+        PatchOutcome(
+            Patch::from_str(&patch_text)
+                .map_err(|err| err.to_string())
+                .and_then(|patch| apply(&base, &patch).map_err(|err| err.to_string())),
+        )
+    }
+
+    /// Apply a multi-file unified diff to the files under `root`.
+    fn applyToTree_(
+        &self,
+        patch_text: WrenString,
+        root: WrenString,
+        strip: f64,
+        fuzz: f64,
+        dry_run: bool,
+    ) -> Result<Value, String> {
+        let patch_text = patch_text.into_string().unwrap_or_default();
+        let root = root.into_string().unwrap_or_default();
+        let count = |n: f64| if n.is_finite() && n > 0.0 { n as usize } else { 0 };
+        patch::apply_to_tree(&patch_text, Path::new(&root), count(strip), count(fuzz), dry_run)
+    }
+
+    fn reverse(&self, patch_text: WrenString) -> Result<String, String> {
+        let patch_text = patch_text.into_string().unwrap_or_default();
+        patch::reverse(&patch_text)
+    }
+
+    /// Three-way merge; `diff3` keeps the base side in conflict regions.
+    fn merge_(&self, base: WrenString, ours: WrenString, theirs: WrenString, diff3: bool) -> Value {
+        let base = base.into_string().unwrap_or_default();
+        let ours = ours.into_string().unwrap_or_default();
+        let theirs = theirs.into_string().unwrap_or_default();
+        merge::merge(&base, &ours, &theirs, diff3).into_value()
+    }
+
+    fn prettyMerge_(
+        &self,
+        path: WrenString,
+        base: WrenString,
+        ours: WrenString,
+        theirs: WrenString,
+        diff3: bool,
+    ) -> String {
+        let path = path.into_string().unwrap_or_default();
+        let base = base.into_string().unwrap_or_default();
+        let ours = ours.into_string().unwrap_or_default();
+        let theirs = theirs.into_string().unwrap_or_default();
+        let merged = merge::merge(&base, &ours, &theirs, diff3);
+        merged.pretty(&pretty_path(&path), |text| {
+            let diff = pretty_diff_text(&path, &base, text, &PrettyOptions::default());
+            // Keep the hunks; the merge header replaces "• Edited".
+            diff.split_once('\n').map(|(_, body)| body.to_string()).unwrap_or_default()
+        })
+    }
+}
+
+fn timeout_from_seconds(seconds: f64) -> Option<Duration> {
+    if seconds < 0.0 {
+        None
+    } else if seconds.is_finite() {
+        Some(Duration::from_secs_f64(seconds))
+    } else {
+        Some(Duration::ZERO)
+    }
+}
+
+fn take_ready_batch(state: &mut NativeWatcherState) -> Vec<NativeEvent> {
+    if !state.queue.is_empty() {
+        state.queue.drain(..).collect()
+    } else {
+        state.batches.pop_front().unwrap_or_default()
     }
 }
 
@@ -539,6 +1395,9 @@ impl NativeWatch {
                 _watcher: watcher,
                 rx,
                 queue: VecDeque::new(),
+                debounce: None,
+                coalescer: EventCoalescer::default(),
+                batches: VecDeque::new(),
             },
         );
         handle as f64
@@ -567,6 +1426,30 @@ impl NativeWatch {
         count as f64
     }
 
+    /// Enable (seconds > 0) or disable (seconds <= 0) debouncing. Events
+    /// already pending in the coalescer are released as a batch on disable.
+    fn debounce(&self, handle: f64, seconds: f64) -> bool {
+        let handle = handle as u64;
+        let mut watchers = get_native_watchers();
+        let map = watchers.as_mut().unwrap();
+
+        let Some(state) = map.get_mut(&handle) else {
+            return false;
+        };
+
+        drain_native_events(state);
+        if seconds > 0.0 && seconds.is_finite() {
+            state.debounce = Some(Duration::from_secs_f64(seconds));
+        } else {
+            state.debounce = None;
+            let batch = state.coalescer.take();
+            if !batch.is_empty() {
+                state.batches.push_back(batch);
+            }
+        }
+        true
+    }
+
     fn pending(&self, handle: f64) -> f64 {
         let handle = handle as u64;
         let mut watchers = get_native_watchers();
@@ -574,30 +1457,60 @@ impl NativeWatch {
 
         if let Some(state) = map.get_mut(&handle) {
             drain_native_events(state);
-            state.queue.len() as f64
+            ready_event_count(state) as f64
         } else {
             -1.0
         }
     }
 
-    fn takeEvent(&self, handle: f64) -> Vec<String> {
+    /// Next ready event map, or `null` when none is queued.
+    fn takeEvent(&self, handle: f64) -> Option<NativeEvent> {
         let handle = handle as u64;
         let mut watchers = get_native_watchers();
         let map = watchers.as_mut().unwrap();
 
-        let Some(state) = map.get_mut(&handle) else {
-            return Vec::new();
-        };
+        let state = map.get_mut(&handle)?;
+        drain_native_events(state);
+        pop_native_event(state)
+    }
 
+    fn waitEvent(&self, handle: f64, timeout_seconds: f64) -> Option<NativeEvent> {
+        let handle = handle as u64;
+        let mut watchers = get_native_watchers();
+        let map = watchers.as_mut().unwrap();
+
+        let state = map.get_mut(&handle)?;
         drain_native_events(state);
-        if let Some(event) = state.queue.pop_front() {
-            encode_native_event(event)
+        if let Some(event) = pop_native_event(state) {
+            return Some(event);
+        }
+
+        if state.debounce.is_some() {
+            wait_for_batch(state, timeout_from_seconds(timeout_seconds));
         } else {
-            Vec::new()
+            let received = if timeout_seconds < 0.0 {
+                state.rx.recv().ok()
+            } else if timeout_seconds > 0.0 && timeout_seconds.is_finite() {
+                state
+                    .rx
+                    .recv_timeout(Duration::from_secs_f64(timeout_seconds))
+                    .ok()
+            } else {
+                state.rx.try_recv().ok()
+            };
+
+            if let Some(result) = received {
+                accept_native_result(state, result);
+                drain_native_events(state);
+            }
         }
+
+        pop_native_event(state)
     }
 
-    fn waitEvent(&self, handle: f64, timeout_seconds: f64) -> Vec<String> {
+    /// Take the next ready batch of coalesced events (empty when none).
+    /// Without debouncing, every queued event is returned as one batch.
+    fn takeBatch(&self, handle: f64) -> Vec<NativeEvent> {
         let handle = handle as u64;
         let mut watchers = get_native_watchers();
         let map = watchers.as_mut().unwrap();
@@ -607,38 +1520,40 @@ impl NativeWatch {
         };
 
         drain_native_events(state);
-        if let Some(event) = state.queue.pop_front() {
-            return encode_native_event(event);
-        }
+        take_ready_batch(state)
+    }
 
-        let received = if timeout_seconds < 0.0 {
-            state.rx.recv().ok()
-        } else if timeout_seconds > 0.0 && timeout_seconds.is_finite() {
-            state
-                .rx
-                .recv_timeout(Duration::from_secs_f64(timeout_seconds))
-                .ok()
-        } else {
-            state.rx.try_recv().ok()
+    fn waitBatch(&self, handle: f64, timeout_seconds: f64) -> Vec<NativeEvent> {
+        let handle = handle as u64;
+        let mut watchers = get_native_watchers();
+        let map = watchers.as_mut().unwrap();
+
+        let Some(state) = map.get_mut(&handle) else {
+            return Vec::new();
         };
 
-        if let Some(result) = received {
-            match result {
-                Ok(event) => state.queue.push_back(normalize_event(event)),
-                Err(err) => state.queue.push_back(NativeEvent {
-                    kind: "error".to_string(),
-                    timestamp: now_unix_seconds(),
-                    paths: vec![err.to_string()],
-                }),
+        drain_native_events(state);
+        if ready_event_count(state) == 0 {
+            if state.debounce.is_some() {
+                wait_for_batch(state, timeout_from_seconds(timeout_seconds));
+            } else {
+                let received = if timeout_seconds < 0.0 {
+                    state.rx.recv().ok()
+                } else if timeout_seconds > 0.0 && timeout_seconds.is_finite() {
+                    state
+                        .rx
+                        .recv_timeout(Duration::from_secs_f64(timeout_seconds))
+                        .ok()
+                } else {
+                    None
+                };
+                if let Some(result) = received {
+                    accept_native_result(state, result);
+                    drain_native_events(state);
+                }
             }
-            drain_native_events(state);
-        }
-
-        if let Some(event) = state.queue.pop_front() {
-            encode_native_event(event)
-        } else {
-            Vec::new()
         }
+        take_ready_batch(state)
     }
 }
 
@@ -647,8 +1562,10 @@ ruwren::wren_module! {
         pub crate::stdlib::file::File;
         pub crate::stdlib::file::Dir;
         pub crate::stdlib::file::PathUtil;
+        pub crate::stdlib::file::TempUtil;
         pub crate::stdlib::file::NativeWatch;
         pub crate::stdlib::file::DiffUtil;
+        pub crate::stdlib::handle::FileHandle;
     }
 }
 
@@ -0,0 +1,303 @@
+//! `FileHandle`: streaming reads/writes for files too large to load with
+//! `File.read`.
+//!
+//! Reads go through a `BufReader`; writes are collected in a small pending
+//! buffer and written at the reader's logical position, so a handle opened
+//! with `"r+"` can mix both.
+
+#![allow(non_snake_case)]
+
+use crate::stdlib::process::is_dry_run;
+use crate::stdlib::value::RawText;
+use ruwren::foreign_v2::WrenString;
+use ruwren::{WrenObject, wren_impl};
+use std::cell::{RefCell, RefMut};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::rc::Rc;
+
+const PENDING_LIMIT: usize = 64 * 1024;
+
+struct Mode {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+}
+
+fn parse_mode(mode: &str) -> Option<Mode> {
+    let mode = mode.replace('b', "");
+    let (read, write, append, truncate) = match mode.as_str() {
+        "r" => (true, false, false, false),
+        "r+" => (true, true, false, false),
+        "w" => (false, true, false, true),
+        "w+" => (true, true, false, true),
+        "a" => (false, true, true, false),
+        "a+" => (true, true, true, false),
+        _ => return None,
+    };
+    Some(Mode {
+        read,
+        write,
+        append,
+        truncate,
+    })
+}
+
+struct OpenFile {
+    reader: BufReader<fs::File>,
+    pending: Vec<u8>,
+}
+
+impl OpenFile {
+    fn flush_pending(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        // Drop read-ahead so the write lands at the logical position.
+        if !self.reader.buffer().is_empty() {
+            let position = self.reader.stream_position()?;
+            self.reader.seek(SeekFrom::Start(position))?;
+        }
+        self.reader.get_mut().write_all(&self.pending)?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Drop for OpenFile {
+    /// Handles collected without `close()` still write what they buffered.
+    fn drop(&mut self) {
+        let _ = self.flush_pending();
+    }
+}
+
+#[derive(WrenObject, Default)]
+pub struct FileHandle {
+    path: String,
+    /// Shared only because WrenObject fields must be `Clone`.
+    file: Option<Rc<RefCell<OpenFile>>>,
+    open: bool,
+    readable: bool,
+    writable: bool,
+    /// Bytes a writable handle would have written under `--dry-run`.
+    dry_run_bytes: Option<usize>,
+}
+
+fn closed_error() -> String {
+    "FileHandle: handle is closed".to_string()
+}
+
+impl FileHandleInstance {
+    /// The open file with pending writes flushed, `None` for a dry-run
+    /// handle with nothing to read.
+    fn file(&self) -> Result<Option<RefMut<'_, OpenFile>>, String> {
+        if !self.open {
+            return Err(closed_error());
+        }
+        let path = &self.path;
+        let Some(file) = self.file.as_ref() else {
+            return Ok(None);
+        };
+        let mut file = file.borrow_mut();
+        file.flush_pending()
+            .map_err(|err| format!("FileHandle: {path}: {err}"))?;
+        Ok(Some(file))
+    }
+
+    fn io_error(&self, err: io::Error) -> String {
+        format!("FileHandle: {}: {err}", self.path)
+    }
+}
+
+#[wren_impl]
+impl FileHandle {
+    /// `mode` is one of `r`, `r+`, `w`, `w+`, `a`, `a+` (a `b` is ignored).
+    #[wren_impl(constructor)]
+    fn open(&self, path: WrenString, mode: WrenString) -> Result<FileHandleInstance, String> {
+        let path = path.into_string().unwrap_or_default();
+        let mode_name = mode.into_string().unwrap_or_default();
+        let Some(mode) = parse_mode(&mode_name) else {
+            return Err(format!("FileHandle: invalid mode '{mode_name}'"));
+        };
+
+        // Under --dry-run a writable handle never touches the file: reads
+        // see the current content (unless the mode would truncate it) and
+        // writes are only counted.
+        let dry_run = mode.write && is_dry_run();
+        let file = if dry_run {
+            if mode.read && !mode.truncate {
+                fs::File::open(&path).ok()
+            } else {
+                None
+            }
+        } else {
+            let file = fs::OpenOptions::new()
+                .read(mode.read)
+                .write(mode.write && !mode.append)
+                .append(mode.append)
+                .create(mode.write)
+                .truncate(mode.truncate)
+                .open(&path)
+                .map_err(|err| format!("FileHandle: {path}: {err}"))?;
+            Some(file)
+        };
+        if dry_run {
+            println!("[dry-run] open {path} ({mode_name})");
+        }
+
+        Ok(FileHandleInstance {
+            path,
+            file: file.map(|file| {
+                Rc::new(RefCell::new(OpenFile {
+                    reader: BufReader::new(file),
+                    pending: Vec::new(),
+                }))
+            }),
+            open: true,
+            readable: mode.read,
+            writable: mode.write,
+            dry_run_bytes: dry_run.then_some(0),
+        })
+    }
+
+    #[wren_impl(instance, getter)]
+    fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    #[wren_impl(instance, getter)]
+    fn isOpen(&self) -> bool {
+        self.open
+    }
+
+    /// Next line without its `\n` / `\r\n`, or `null` at end of file.
+    #[wren_impl(instance)]
+    fn readLine(&mut self) -> Result<Option<RawText>, String> {
+        if !self.readable {
+            return Err(format!("FileHandle: {} is not open for reading", self.path));
+        }
+        let Some(mut file) = self.file()? else {
+            return Ok(None);
+        };
+        let mut line = Vec::new();
+        let read = file.reader.read_until(b'\n', &mut line);
+        match read {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+                Ok(Some(RawText(line)))
+            }
+            Err(err) => Err(self.io_error(err)),
+        }
+    }
+
+    /// Up to `count` bytes, or `null` at end of file.
+    #[wren_impl(instance)]
+    fn read(&mut self, count: f64) -> Result<Option<RawText>, String> {
+        if !self.readable {
+            return Err(format!("FileHandle: {} is not open for reading", self.path));
+        }
+        let Some(mut file) = self.file()? else {
+            return Ok(None);
+        };
+        let count = if count.is_finite() && count > 0.0 {
+            count as u64
+        } else {
+            0
+        };
+        let mut buf = Vec::new();
+        let read = file.reader.by_ref().take(count).read_to_end(&mut buf);
+        match read {
+            Ok(0) if count > 0 => Ok(None),
+            Ok(_) => Ok(Some(RawText(buf))),
+            Err(err) => Err(self.io_error(err)),
+        }
+    }
+
+    #[wren_impl(instance)]
+    fn write_(&mut self, text: WrenString) -> Result<bool, String> {
+        if !self.open {
+            return Err(closed_error());
+        }
+        if !self.writable {
+            return Err(format!("FileHandle: {} is not open for writing", self.path));
+        }
+        if let Some(count) = self.dry_run_bytes.as_mut() {
+            *count += text.bytes().len();
+            return Ok(true);
+        }
+        let Some(file) = self.file.as_ref() else {
+            return Ok(false);
+        };
+        let mut file = file.borrow_mut();
+        file.pending.extend_from_slice(text.bytes());
+        if file.pending.len() >= PENDING_LIMIT {
+            file.flush_pending().map_err(|err| self.io_error(err))?;
+        }
+        Ok(true)
+    }
+
+    /// Move to byte offset `position` from the start; returns the new offset.
+    #[wren_impl(instance)]
+    fn seek(&mut self, position: f64) -> Result<f64, String> {
+        if !position.is_finite() || position < 0.0 {
+            return Err(format!("FileHandle: invalid seek position {position}"));
+        }
+        let Some(mut file) = self.file()? else {
+            return Ok(0.0);
+        };
+        let seeked = file.reader.seek(SeekFrom::Start(position as u64));
+        seeked
+            .map(|pos| pos as f64)
+            .map_err(|err| self.io_error(err))
+    }
+
+    #[wren_impl(instance, getter)]
+    fn position(&mut self) -> Result<f64, String> {
+        let Some(mut file) = self.file()? else {
+            return Ok(0.0);
+        };
+        let position = file.reader.stream_position();
+        position
+            .map(|pos| pos as f64)
+            .map_err(|err| self.io_error(err))
+    }
+
+    #[wren_impl(instance)]
+    fn flush(&mut self) -> Result<bool, String> {
+        let Some(mut file) = self.file()? else {
+            return Ok(true);
+        };
+        let flushed = file.reader.get_mut().flush();
+        flushed.map(|_| true).map_err(|err| self.io_error(err))
+    }
+
+    /// Flush and release the file. Closing twice is a no-op.
+    #[wren_impl(instance)]
+    fn close(&mut self) -> Result<bool, String> {
+        if !self.open {
+            return Ok(true);
+        }
+        if let Some(count) = self.dry_run_bytes {
+            println!("[dry-run] write {} ({count} bytes)", self.path);
+        }
+        let flushed = match self.file() {
+            Ok(Some(mut file)) => file
+                .reader
+                .get_mut()
+                .flush()
+                .map_err(|err| self.io_error(err)),
+            Ok(None) => Ok(()),
+            Err(err) => Err(err),
+        };
+        self.open = false;
+        self.file = None;
+        flushed.map(|_| true)
+    }
+}
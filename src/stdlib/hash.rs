@@ -0,0 +1,190 @@
+#![allow(non_snake_case)]
+
+use crate::stdlib::bytes::{Bytes, BytesInstance};
+use crate::stdlib::tree::{self, TreeOptions};
+use crate::stdlib::value::Value;
+use ruwren::foreign_v2::WrenString;
+use ruwren::{ModuleLibrary, WrenObject, wren_impl};
+use sha1::Digest;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Sha1(sha1::Sha1),
+    Md5(md5::Md5),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    /// Accepts `sha256`, `SHA-256`, `md5`, `blake3`, `crc32`, ...
+    fn new(algorithm: &str) -> Result<Self, String> {
+        let name = algorithm.to_ascii_lowercase().replace(['-', '_'], "");
+        Ok(match name.as_str() {
+            "sha256" => Hasher::Sha256(sha2::Sha256::new()),
+            "sha1" => Hasher::Sha1(sha1::Sha1::new()),
+            "md5" => Hasher::Md5(md5::Md5::new()),
+            "blake3" | "b3" => Hasher::Blake3(Box::default()),
+            "crc32" => Hasher::Crc32(crc32fast::Hasher::new()),
+            _ => return Err(format!("Hash: unknown algorithm '{algorithm}'")),
+        })
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha1(h) => h.finalize().to_vec(),
+            Hasher::Md5(h) => h.finalize().to_vec(),
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+            Hasher::Crc32(h) => h.finalize().to_be_bytes().to_vec(),
+        }
+    }
+}
+
+fn digest(algorithm: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut hasher = Hasher::new(algorithm)?;
+    hasher.update(data);
+    Ok(hasher.finish())
+}
+
+/// Hash a file in fixed-size chunks, so memory use does not grow with it.
+fn digest_file(algorithm: &str, path: &Path) -> Result<Vec<u8>, String> {
+    let mut hasher = Hasher::new(algorithm)?;
+    let mut file =
+        fs::File::open(path).map_err(|err| format!("Hash: {}: {err}", path.display()))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(format!("Hash: {}: {err}", path.display())),
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// Algorithm implied by the length of a hex digest, for `verifyFile`
+/// without an explicit algorithm (64 hex digits means SHA-256, not BLAKE3).
+fn algorithm_for_hex(hex: &str) -> Option<&'static str> {
+    match hex.len() {
+        8 => Some("crc32"),
+        32 => Some("md5"),
+        40 => Some("sha1"),
+        64 => Some("sha256"),
+        _ => None,
+    }
+}
+
+#[derive(WrenObject, Default)]
+pub struct Hash;
+
+#[wren_impl]
+impl Hash {
+    /// Hex digest of a String's raw bytes.
+    fn string_(&self, algorithm: WrenString, text: WrenString) -> Result<String, String> {
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        digest(&algorithm, text.bytes()).map(hex::encode)
+    }
+
+    #[wren_impl(object(bytes))]
+    fn bytes_(&self, algorithm: WrenString, bytes: Option<Bytes>) -> Result<String, String> {
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        let Some(bytes) = bytes else {
+            return Err("Hash: expected a String or Bytes".to_string());
+        };
+        digest(&algorithm, &bytes.into_vec()).map(hex::encode)
+    }
+
+    fn file(&self, path: WrenString, algorithm: WrenString) -> Result<String, String> {
+        let path = path.into_string().unwrap_or_default();
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        digest_file(&algorithm, Path::new(&path)).map(hex::encode)
+    }
+
+    /// `true` when the file's digest matches `expected` (case-insensitive).
+    /// An empty `algorithm` is inferred from the digest length.
+    fn verify_(
+        &self,
+        path: WrenString,
+        expected: WrenString,
+        algorithm: WrenString,
+    ) -> Result<bool, String> {
+        let path = path.into_string().unwrap_or_default();
+        let expected = expected
+            .into_string()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        let algorithm = if algorithm.is_empty() {
+            match algorithm_for_hex(&expected) {
+                Some(algorithm) => algorithm.to_string(),
+                None => return Err(format!("Hash: cannot infer algorithm for '{expected}'")),
+            }
+        } else {
+            algorithm
+        };
+        if !Path::new(&path).is_file() {
+            return Ok(false);
+        }
+        Ok(digest_file(&algorithm, Path::new(&path))? == hex::decode(&expected).unwrap_or_default())
+    }
+
+    /// `[[relativePath, hex], ...]` for every regular file under `dir`, in
+    /// `tree::walk` order with its excludes, skipping `skip` at the top level.
+    fn tree_(
+        &self,
+        dir: WrenString,
+        algorithm: WrenString,
+        skip: WrenString,
+        exclude: WrenString,
+    ) -> Result<Value, String> {
+        let dir = PathBuf::from(dir.into_string().unwrap_or_default());
+        let algorithm = algorithm.into_string().unwrap_or_default();
+        let skip = PathBuf::from(skip.into_string().unwrap_or_default());
+        let exclude = exclude.into_string().unwrap_or_default();
+        let options = TreeOptions {
+            exclude: tree::build_excludes(&exclude).map_err(|err| format!("Hash: {err}"))?,
+            ..TreeOptions::default()
+        };
+        let mut files = Vec::new();
+        tree::walk(&dir, Path::new(""), &options, &mut |rel, meta| {
+            if meta.is_file() && rel != skip {
+                files.push(rel.to_path_buf());
+            }
+        })
+        .map_err(|err| format!("Hash: {}: {err}", dir.display()))?;
+
+        let mut entries = Vec::new();
+        for rel in files {
+            let hex = hex::encode(digest_file(&algorithm, &dir.join(&rel))?);
+            entries.push(Value::List(vec![tree::display(&rel).into(), hex.into()]));
+        }
+        Ok(Value::List(entries))
+    }
+}
+
+ruwren::wren_module! {
+    mod wrun_hash {
+        pub crate::stdlib::hash::Hash;
+    }
+}
+
+pub fn publish_module(lib: &mut ModuleLibrary) {
+    wrun_hash::publish_module(lib);
+}
@@ -0,0 +1,627 @@
+//! Display-width aware text layout: `Table` and `Box` in `wrun/print`, and
+//! the line fitting used by regions and the live line.
+//!
+//! Widths count terminal cells, not chars: CJK and emoji take two cells,
+//! combining marks and escape sequences none.
+
+#![allow(non_snake_case)]
+
+use ruwren::foreign_v2::WrenString;
+use ruwren::{WrenObject, wren_impl};
+
+const RESET: &str = "\x1b[0m";
+
+/// Ranges of East Asian wide and fullwidth characters, and emoji that
+/// terminals draw two cells wide.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F3),
+    (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA),
+    (0x26FD, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274C),
+    (0x274E, 0x274E),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B50),
+    (0x2B55, 0x2B55),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F200, 0x1F251),
+    (0x1F300, 0x1F64F),
+    (0x1F680, 0x1F6FF),
+    (0x1F7E0, 0x1F7EB),
+    (0x1F90C, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+/// Combining marks, joiners and variation selectors.
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0E31, 0x0E31),
+    (0x0E34, 0x0E3A),
+    (0x0E47, 0x0E4E),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F),
+    (0x202A, 0x202E),
+    (0x2060, 0x2064),
+    (0x20D0, 0x20FF),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFEFF, 0xFEFF),
+    (0x1F3FB, 0x1F3FF),
+    (0xE0100, 0xE01EF),
+];
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Terminal cells taken by `c`.
+pub fn char_width(c: char) -> usize {
+    if c.is_control() || in_ranges(c, ZERO_WIDTH) {
+        0
+    } else if in_ranges(c, WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+enum Piece<'a> {
+    Text(char),
+    Escape(&'a str),
+}
+
+/// `text` split into chars and whole escape sequences.
+fn pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\x1b' {
+            pieces.push(Piece::Text(c));
+            continue;
+        }
+        let mut end = start + 1;
+        for (idx, next) in chars.by_ref() {
+            end = idx + next.len_utf8();
+            if next.is_ascii_alphabetic() {
+                break;
+            }
+        }
+        pieces.push(Piece::Escape(&text[start..end]));
+    }
+    pieces
+}
+
+pub fn display_width(text: &str) -> usize {
+    pieces(text)
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(c) => char_width(*c),
+            Piece::Escape(_) => 0,
+        })
+        .sum()
+}
+
+/// `text` cut to `width` cells with a trailing `…`. Escape sequences are
+/// kept, and a cut styled line is reset so the style does not leak.
+pub fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    let mut styled = false;
+    for piece in pieces(text) {
+        match piece {
+            Piece::Escape(seq) => {
+                out.push_str(seq);
+                styled = true;
+            }
+            Piece::Text(c) => {
+                let cells = char_width(c);
+                if used + cells + 1 > width {
+                    break;
+                }
+                out.push(c);
+                used += cells;
+            }
+        }
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    if styled {
+        out.push_str(RESET);
+    }
+    out
+}
+
+/// `text` broken into lines of at most `width` cells, preferring spaces.
+/// Colour that is active at a break is carried onto the next line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut used = 0;
+    // Byte offset of the last space in `line`, for breaking between words.
+    let mut last_space: Option<usize> = None;
+    let mut active = String::new();
+    for piece in pieces(text) {
+        let c = match piece {
+            Piece::Escape(seq) => {
+                line.push_str(seq);
+                if seq == RESET || seq == "\x1b[m" {
+                    active.clear();
+                } else if seq.ends_with('m') {
+                    active.push_str(seq);
+                }
+                continue;
+            }
+            Piece::Text(c) => c,
+        };
+        let cells = char_width(c);
+        if used + cells > width {
+            let rest = match last_space.take() {
+                Some(space) if c != ' ' => {
+                    let rest = line[space + 1..].to_string();
+                    line.truncate(space);
+                    rest
+                }
+                _ => String::new(),
+            };
+            if !active.is_empty() {
+                line.push_str(RESET);
+            }
+            lines.push(std::mem::take(&mut line));
+            line.push_str(&active);
+            line.push_str(&rest);
+            used = display_width(&rest);
+            if c == ' ' {
+                continue;
+            }
+        }
+        if c == ' ' {
+            last_space = Some(line.len());
+        }
+        line.push(c);
+        used += cells;
+    }
+    lines.push(line);
+    lines
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "right" => Align::Right,
+            "center" => Align::Center,
+            _ => Align::Left,
+        }
+    }
+}
+
+fn pad(text: &str, width: usize, align: Align) -> String {
+    let gap = width.saturating_sub(display_width(text));
+    let (left, right) = match align {
+        Align::Left => (0, gap),
+        Align::Right => (gap, 0),
+        Align::Center => (gap / 2, gap - gap / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Overflow {
+    Truncate,
+    Wrap,
+}
+
+/// `text` as lines of at most `width` cells.
+fn fit(text: &str, width: usize, overflow: Overflow) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| match overflow {
+            Overflow::Truncate => vec![truncate(line, width)],
+            Overflow::Wrap => wrap(line, width),
+        })
+        .collect()
+}
+
+/// Line-drawing characters, clockwise from the top-left corner.
+struct Border {
+    top_left: &'static str,
+    top: &'static str,
+    top_joint: &'static str,
+    top_right: &'static str,
+    side: &'static str,
+    left_joint: &'static str,
+    cross: &'static str,
+    right_joint: &'static str,
+    bottom_left: &'static str,
+    bottom_joint: &'static str,
+    bottom_right: &'static str,
+}
+
+impl Border {
+    const fn new(chars: [&'static str; 11]) -> Self {
+        let [
+            top_left,
+            top,
+            top_joint,
+            top_right,
+            side,
+            left_joint,
+            cross,
+            right_joint,
+            bottom_left,
+            bottom_joint,
+            bottom_right,
+        ] = chars;
+        Border {
+            top_left,
+            top,
+            top_joint,
+            top_right,
+            side,
+            left_joint,
+            cross,
+            right_joint,
+            bottom_left,
+            bottom_joint,
+            bottom_right,
+        }
+    }
+
+    /// `None` for `"none"`: columns separated by spaces, no frame.
+    fn parse(name: &str) -> Result<Option<Self>, String> {
+        let chars = match name {
+            "rounded" => ["╭", "─", "┬", "╮", "│", "├", "┼", "┤", "╰", "┴", "╯"],
+            "single" => ["┌", "─", "┬", "┐", "│", "├", "┼", "┤", "└", "┴", "┘"],
+            "double" => ["╔", "═", "╦", "╗", "║", "╠", "╬", "╣", "╚", "╩", "╝"],
+            "heavy" => ["┏", "━", "┳", "┓", "┃", "┣", "╋", "┫", "┗", "┻", "┛"],
+            "ascii" => ["+", "-", "+", "+", "|", "+", "+", "+", "+", "+", "+"],
+            "none" => return Ok(None),
+            _ => {
+                return Err(format!(
+                    "unknown border '{name}', expected rounded, single, double, heavy, ascii or none"
+                ));
+            }
+        };
+        Ok(Some(Border::new(chars)))
+    }
+
+    fn rule(&self, widths: &[usize], left: &str, joint: &str, right: &str) -> String {
+        let segments: Vec<String> = widths
+            .iter()
+            .map(|width| self.top.repeat(width + 2))
+            .collect();
+        format!("{left}{}{right}", segments.join(joint))
+    }
+}
+
+/// Narrowest a column is squeezed to before the table overflows instead.
+const MIN_COLUMN: usize = 3;
+
+/// Shrink the widest columns until the table fits in `max_width` cells.
+fn shrink_columns(widths: &mut [usize], chrome: usize, max_width: usize) {
+    while widths.iter().sum::<usize>() + chrome > max_width {
+        let Some(widest) = widths
+            .iter_mut()
+            .filter(|width| **width > MIN_COLUMN)
+            .max_by_key(|width| **width)
+        else {
+            break;
+        };
+        *widest -= 1;
+    }
+}
+
+pub struct Table<'a> {
+    pub header: Option<Vec<&'a str>>,
+    pub rows: Vec<Vec<&'a str>>,
+    pub aligns: Vec<Align>,
+}
+
+impl Table<'_> {
+    pub fn render(
+        &self,
+        border: &str,
+        max_width: Option<usize>,
+        overflow: Overflow,
+    ) -> Result<String, String> {
+        let border = Border::parse(border)?;
+        let columns = self
+            .header
+            .iter()
+            .chain(&self.rows)
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+        if columns == 0 {
+            return Ok(String::new());
+        }
+        let mut widths = vec![0; columns];
+        for row in self.header.iter().chain(&self.rows) {
+            for (column, cell) in row.iter().enumerate() {
+                let width = cell.lines().map(display_width).max().unwrap_or(0);
+                widths[column] = widths[column].max(width);
+            }
+        }
+        let chrome = match border {
+            Some(_) => 3 * columns + 1,
+            None => 2 * (columns - 1),
+        };
+        if let Some(max_width) = max_width {
+            shrink_columns(&mut widths, chrome, max_width);
+        }
+
+        let render_row = |row: &[&str], out: &mut Vec<String>| {
+            let cells: Vec<Vec<String>> = (0..columns)
+                .map(|column| {
+                    fit(
+                        row.get(column).copied().unwrap_or(""),
+                        widths[column],
+                        overflow,
+                    )
+                })
+                .collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+            for line in 0..height {
+                let padded: Vec<String> = cells
+                    .iter()
+                    .enumerate()
+                    .map(|(column, cell)| {
+                        let text = cell.get(line).map_or("", String::as_str);
+                        let align = self.aligns.get(column).copied().unwrap_or(Align::Left);
+                        pad(text, widths[column], align)
+                    })
+                    .collect();
+                out.push(match &border {
+                    Some(border) => {
+                        let side = border.side;
+                        format!("{side} {} {side}", padded.join(&format!(" {side} ")))
+                    }
+                    None => padded.join("  ").trim_end().to_string(),
+                });
+            }
+        };
+
+        let mut lines = Vec::new();
+        if let Some(border) = &border {
+            lines.push(border.rule(&widths, border.top_left, border.top_joint, border.top_right));
+        }
+        if let Some(header) = &self.header {
+            render_row(header, &mut lines);
+            match &border {
+                Some(border) => lines.push(border.rule(
+                    &widths,
+                    border.left_joint,
+                    border.cross,
+                    border.right_joint,
+                )),
+                None => lines.push(
+                    widths
+                        .iter()
+                        .map(|width| "─".repeat(*width))
+                        .collect::<Vec<_>>()
+                        .join("  "),
+                ),
+            }
+        }
+        for row in &self.rows {
+            render_row(row, &mut lines);
+        }
+        if let Some(border) = &border {
+            lines.push(border.rule(
+                &widths,
+                border.bottom_left,
+                border.bottom_joint,
+                border.bottom_right,
+            ));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+/// `body` framed by `border`, with `title` set into the top edge.
+pub fn draw_box(
+    title: &str,
+    body: &str,
+    border: &str,
+    max_width: Option<usize>,
+    overflow: Overflow,
+) -> Result<String, String> {
+    let border = Border::parse(border)?.unwrap_or(Border::new([" "; 11]));
+    let natural = body
+        .lines()
+        .map(display_width)
+        .max()
+        .unwrap_or(0)
+        .max(if title.is_empty() {
+            0
+        } else {
+            display_width(title) + 2
+        });
+    let width = match max_width {
+        Some(max_width) => natural.min(max_width.saturating_sub(4)).max(1),
+        None => natural.max(1),
+    };
+
+    let mut lines = Vec::new();
+    let top = if title.is_empty() {
+        border.top.repeat(width + 2)
+    } else {
+        let title = truncate(title, width.saturating_sub(2));
+        let fill = (width + 2).saturating_sub(display_width(&title) + 3);
+        format!("{} {title} {}", border.top, border.top.repeat(fill))
+    };
+    lines.push(format!("{}{top}{}", border.top_left, border.top_right));
+    for line in fit(body, width, overflow) {
+        lines.push(format!(
+            "{} {} {}",
+            border.side,
+            pad(&line, width, Align::Left),
+            border.side
+        ));
+    }
+    lines.push(format!(
+        "{}{}{}",
+        border.bottom_left,
+        border.top.repeat(width + 2),
+        border.bottom_right
+    ));
+    Ok(lines.join("\n"))
+}
+
+/// Options resolved on the Wren side: max width 0 means unlimited and a
+/// negative one the terminal width.
+fn max_width(width: f64) -> Option<usize> {
+    if width < 0.0 {
+        crate::stdlib::term::terminal_width()
+    } else if width > 0.0 {
+        Some(width as usize)
+    } else {
+        None
+    }
+}
+
+fn overflow(name: &str) -> Overflow {
+    if name == "wrap" {
+        Overflow::Wrap
+    } else {
+        Overflow::Truncate
+    }
+}
+
+#[derive(WrenObject, Default)]
+pub struct LayoutInternal;
+
+#[wren_impl]
+impl LayoutInternal {
+    fn displayWidth(&self, text: WrenString) -> f64 {
+        display_width(&text.into_string().unwrap_or_default()) as f64
+    }
+
+    /// `cells` is every cell, header first when `header` is set, joined by
+    /// `\x00`; `columns` cells make a row.
+    #[allow(clippy::too_many_arguments)]
+    fn renderTable(
+        &self,
+        columns: f64,
+        cells: WrenString,
+        header: bool,
+        aligns: WrenString,
+        border: WrenString,
+        width: f64,
+        overflow_name: WrenString,
+    ) -> Result<String, String> {
+        let cells = cells.into_string().unwrap_or_default();
+        let aligns = aligns.into_string().unwrap_or_default();
+        let columns = (columns as usize).max(1);
+        let mut rows: Vec<Vec<&str>> = if cells.is_empty() {
+            Vec::new()
+        } else {
+            let cells: Vec<&str> = cells.split('\x00').collect();
+            cells.chunks(columns).map(<[&str]>::to_vec).collect()
+        };
+        let header = if header && !rows.is_empty() {
+            Some(rows.remove(0))
+        } else {
+            None
+        };
+        let table = Table {
+            header,
+            rows,
+            aligns: aligns.split('\x00').map(Align::parse).collect(),
+        };
+        table
+            .render(
+                &border.into_string().unwrap_or_default(),
+                max_width(width),
+                overflow(&overflow_name.into_string().unwrap_or_default()),
+            )
+            .map_err(|err| format!("Table: {err}"))
+    }
+
+    fn drawBox(
+        &self,
+        title: WrenString,
+        body: WrenString,
+        border: WrenString,
+        width: f64,
+        overflow_name: WrenString,
+    ) -> Result<String, String> {
+        draw_box(
+            &title.into_string().unwrap_or_default(),
+            &body.into_string().unwrap_or_default(),
+            &border.into_string().unwrap_or_default(),
+            max_width(width),
+            overflow(&overflow_name.into_string().unwrap_or_default()),
+        )
+        .map_err(|err| format!("Box: {err}"))
+    }
+}
@@ -0,0 +1,235 @@
+//! Three-way merge behind `Diff.merge` and `Diff.prettyMerge`, on top of
+//! diffy's merge.
+//!
+//! diffy returns only text, so conflicts are recovered from its markers.
+//! A diff3-style pass always runs as well, so every conflict reports the
+//! base side even when the caller asked for plain conflict markers. That
+//! pass uses markers longer than any marker-like line in the inputs, so a
+//! content line such as a Markdown `=======` underline is never mistaken
+//! for one.
+
+use crate::stdlib::value::Value;
+use diffy::{ConflictStyle, MergeOptions};
+use std::fmt::Write;
+
+/// Length of the markers in the text handed back to scripts, as git writes.
+const MARKER_LEN: usize = 7;
+
+struct Markers {
+    ours: String,
+    base: String,
+    split: String,
+    theirs: String,
+}
+
+impl Markers {
+    fn new(len: usize) -> Markers {
+        Markers {
+            ours: format!("{} ours", "<".repeat(len)),
+            base: format!("{} original", "|".repeat(len)),
+            split: "=".repeat(len),
+            theirs: format!("{} theirs", ">".repeat(len)),
+        }
+    }
+}
+
+/// One more than the longest run of a marker character starting any line
+/// of `texts`, and at least `MARKER_LEN`.
+fn unique_marker_len(texts: &[&str]) -> usize {
+    let longest = texts
+        .iter()
+        .flat_map(|text| text.lines())
+        .map(|line| match line.chars().next() {
+            Some(first @ ('<' | '|' | '=' | '>')) => {
+                line.chars().take_while(|&c| c == first).count()
+            }
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0);
+    (longest + 1).max(MARKER_LEN)
+}
+
+#[derive(Default)]
+struct Conflict {
+    /// 1-based lines of the `<<<<<<<` and `>>>>>>>` markers.
+    start: usize,
+    end: usize,
+    ours: String,
+    base: String,
+    theirs: String,
+}
+
+impl Conflict {
+    /// The side line `number` of the merged text is on, or `None` for one
+    /// of this conflict's markers.
+    fn side_at(&self, number: usize, diff3: bool) -> Option<Side> {
+        let count = |text: &str| text.split_inclusive('\n').count();
+        if number < self.start || number > self.end {
+            return Some(Side::Outside);
+        }
+        let base_marker = self.start + count(&self.ours) + 1;
+        let split = if diff3 {
+            base_marker + count(&self.base) + 1
+        } else {
+            base_marker
+        };
+        if number == self.start || number == split || number == self.end {
+            None
+        } else if number > split {
+            Some(Side::Theirs)
+        } else if !diff3 || number < base_marker {
+            Some(Side::Ours)
+        } else if number == base_marker {
+            None
+        } else {
+            Some(Side::Base)
+        }
+    }
+}
+
+pub struct Merged {
+    text: String,
+    conflicts: Vec<Conflict>,
+    /// Whether `text` has the base sections.
+    diff3: bool,
+}
+
+#[derive(PartialEq)]
+enum Side {
+    Outside,
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// Conflicts in a diff3-style merge written with `len`-long markers. A
+/// marker only counts in the state it belongs to: a split outside an open
+/// conflict or after the split is content.
+fn parse_conflicts(text: &str, len: usize) -> Vec<Conflict> {
+    let markers = Markers::new(len);
+    let mut conflicts = Vec::new();
+    let mut current = Conflict::default();
+    let mut side = Side::Outside;
+    for (idx, line) in text.split_inclusive('\n').enumerate() {
+        let marker = line.strip_suffix('\n').unwrap_or(line);
+        match side {
+            Side::Outside if marker == markers.ours => {
+                current.start = idx + 1;
+                side = Side::Ours;
+            }
+            Side::Ours if marker == markers.base => side = Side::Base,
+            Side::Ours | Side::Base if marker == markers.split => side = Side::Theirs,
+            Side::Theirs if marker == markers.theirs => {
+                current.end = idx + 1;
+                conflicts.push(std::mem::take(&mut current));
+                side = Side::Outside;
+            }
+            Side::Ours => current.ours.push_str(line),
+            Side::Base => current.base.push_str(line),
+            Side::Theirs => current.theirs.push_str(line),
+            Side::Outside => {}
+        }
+    }
+    conflicts
+}
+
+fn run(base: &str, ours: &str, theirs: &str, style: ConflictStyle, len: usize) -> String {
+    let mut options = MergeOptions::new();
+    options.set_conflict_style(style);
+    options.set_conflict_marker_length(len);
+    options
+        .merge(base, ours, theirs)
+        .unwrap_or_else(|conflicted| conflicted)
+}
+
+/// Merge `ours` and `theirs` against `base`; `diff3` also writes the base
+/// side into conflict regions.
+pub fn merge(base: &str, ours: &str, theirs: &str, diff3: bool) -> Merged {
+    let len = unique_marker_len(&[base, ours, theirs]);
+    let structure = run(base, ours, theirs, ConflictStyle::Diff3, len);
+    let mut conflicts = parse_conflicts(&structure, len);
+    let text = if diff3 {
+        // Same lines as `structure`, only the markers may be shorter.
+        run(base, ours, theirs, ConflictStyle::Diff3, MARKER_LEN)
+    } else {
+        // Without the base sections, each conflict moves up by the base
+        // lines (and their marker) dropped at or before it.
+        let mut dropped = 0;
+        for conflict in conflicts.iter_mut() {
+            conflict.start -= dropped;
+            dropped += conflict.base.split_inclusive('\n').count() + 1;
+            conflict.end -= dropped;
+        }
+        run(base, ours, theirs, ConflictStyle::Merge, MARKER_LEN)
+    };
+    Merged {
+        text,
+        conflicts,
+        diff3,
+    }
+}
+
+impl Merged {
+    pub fn into_value(self) -> Value {
+        let conflicts: Vec<Value> = self
+            .conflicts
+            .into_iter()
+            .map(|conflict| {
+                Value::map()
+                    .with("line", conflict.start)
+                    .with("endLine", conflict.end)
+                    .with("ours", conflict.ours)
+                    .with("base", conflict.base)
+                    .with("theirs", conflict.theirs)
+            })
+            .collect();
+        Value::map()
+            .with("ok", conflicts.is_empty())
+            .with("text", self.text)
+            .with("conflicts", Value::List(conflicts))
+    }
+
+    /// Conflict regions with two lines of context, numbered by line of the
+    /// merged text. Clean merges render `clean_body(text)` instead, which
+    /// shows what the merge changed relative to the base.
+    pub fn pretty(&self, path: &str, clean_body: impl FnOnce(&str) -> String) -> String {
+        let mut out = String::new();
+        let count = self.conflicts.len();
+        if count == 0 {
+            let _ = writeln!(out, "• Merged {path} (\x1b[32mclean\x1b[0m)");
+            out.push_str(&clean_body(&self.text));
+            return out;
+        }
+        let noun = if count == 1 { "conflict" } else { "conflicts" };
+        let _ = writeln!(out, "• Merged {path} (\x1b[31m{count} {noun}\x1b[0m)");
+
+        let lines: Vec<&str> = self.text.lines().collect();
+        let width = lines.len().max(1).to_string().len();
+        let mut shown = 0;
+        for (idx, conflict) in self.conflicts.iter().enumerate() {
+            let from = conflict.start.saturating_sub(3).max(shown);
+            let to = (conflict.end + 2).min(lines.len());
+            if idx > 0 && from > shown {
+                let _ = writeln!(out, "{}\x1b[90m⋮\x1b[0m", " ".repeat(width + 3));
+            }
+            for (number, line) in lines.iter().enumerate().take(to).skip(from) {
+                let number = number + 1;
+                let color = match conflict.side_at(number, self.diff3) {
+                    None => "\x1b[1;33m",
+                    Some(Side::Ours) => "\x1b[32m",
+                    Some(Side::Base) => "\x1b[90m",
+                    Some(Side::Theirs) => "\x1b[31m",
+                    Some(Side::Outside) => "",
+                };
+                let reset = if color.is_empty() { "" } else { "\x1b[0m" };
+                let _ = writeln!(
+                    out,
+                    "  \x1b[90m{number:>width$}\x1b[0m {color}{line}{reset}"
+                );
+            }
+            shown = to;
+        }
+        out
+    }
+}
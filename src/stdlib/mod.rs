@@ -1,39 +1,74 @@
 pub mod args;
+mod archive;
+mod bytes;
+mod diff;
 mod env;
 mod file;
+mod handle;
+mod hash;
+mod layout;
+mod merge;
+mod patch;
 pub mod print;
 pub mod process;
+mod progress;
+mod prompt;
+mod search;
 mod str;
+mod style;
+pub mod term;
+mod tree;
+mod value;
 
 use ruwren::ModuleLibrary;
 
+pub use file::cleanup_temp_paths;
+
 pub struct StdlibSource {
     pub process: &'static str,
+    pub bytes: &'static str,
     pub file: &'static str,
+    pub hash: &'static str,
+    pub archive: &'static str,
+    pub search: &'static str,
     pub env: &'static str,
     pub args: &'static str,
     pub str: &'static str,
     pub print: &'static str,
+    pub prompt: &'static str,
+    pub term: &'static str,
     pub pipeline: &'static str,
 }
 
 pub fn get_stdlib_source() -> StdlibSource {
     StdlibSource {
         process: include_str!("wren/process.wren"),
+        bytes: include_str!("wren/bytes.wren"),
         file: include_str!("wren/file.wren"),
+        hash: include_str!("wren/hash.wren"),
+        archive: include_str!("wren/archive.wren"),
+        search: include_str!("wren/search.wren"),
         env: include_str!("wren/env.wren"),
         args: include_str!("wren/args.wren"),
         str: include_str!("wren/str.wren"),
         print: include_str!("wren/print.wren"),
+        prompt: include_str!("wren/prompt.wren"),
+        term: include_str!("wren/term.wren"),
         pipeline: include_str!("wren/pipeline.wren"),
     }
 }
 
 pub fn publish_modules(lib: &mut ModuleLibrary) {
     process::publish_module(lib);
+    bytes::publish_module(lib);
     file::publish_module(lib);
+    hash::publish_module(lib);
+    archive::publish_module(lib);
+    search::publish_module(lib);
     env::publish_module(lib);
     args::publish_module(lib);
     str::publish_module(lib);
     print::publish_module(lib);
+    prompt::publish_module(lib);
+    term::publish_module(lib);
 }
@@ -0,0 +1,838 @@
+//! Multi-file unified diffs (`git diff` / `diff -u` output) behind
+//! `Diff.applyToTree` and `Diff.reverse`.
+//!
+//! Hunks are located the way GNU patch does it: at the line the header
+//! names (shifted by the previous hunk's offset), then at the nearest
+//! matching position either way, then again ignoring up to `fuzz` context
+//! lines at each end. Every file is checked before anything is written, and
+//! diffs for a path already touched apply on top of the earlier result.
+//! New content is then staged next to each target and swapped in, with the
+//! originals kept aside until every file is in place, so a patch applies
+//! completely or not at all even when a write fails.
+
+use crate::stdlib::file::unique_name;
+use crate::stdlib::process::dry_run_skip;
+use crate::stdlib::tree;
+use crate::stdlib::value::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+struct Line {
+    kind: char,
+    text: String,
+    no_newline: bool,
+}
+
+struct Hunk {
+    old_start: usize,
+    old_len: usize,
+    new_start: usize,
+    new_len: usize,
+    section: String,
+    lines: Vec<Line>,
+}
+
+#[derive(Default)]
+struct FilePatch {
+    /// Paths from a `diff --git a/x b/y` line.
+    git: Option<(String, String)>,
+    /// Paths from `---`/`+++`; `None` inside means `/dev/null`.
+    headers: Option<(Option<String>, Option<String>)>,
+    created: Option<String>,
+    deleted: Option<String>,
+    old_mode: Option<String>,
+    new_mode: Option<String>,
+    rename: Option<(String, String)>,
+    index: Option<String>,
+    binary: bool,
+    hunks: Vec<Hunk>,
+}
+
+fn parse_error(line: usize, message: impl std::fmt::Display) -> String {
+    format!("Patch: line {line}: {message}")
+}
+
+/// Path from a `---`/`+++` line: drops a trailing timestamp and quotes.
+fn header_path(raw: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or_default().trim_end();
+    if path == "/dev/null" {
+        return None;
+    }
+    match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(quoted) => Some(quoted.replace("\\\"", "\"").replace("\\\\", "\\")),
+        None => Some(path.to_string()),
+    }
+}
+
+fn git_paths(rest: &str) -> (String, String) {
+    let (old, new) = match rest.find(" b/") {
+        Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+        None => rest.split_once(' ').unwrap_or((rest, rest)),
+    };
+    (old.to_string(), new.to_string())
+}
+
+/// `"12,3"` or `"12"` (length 1).
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let (old_start, old_len) = parse_range(old)?;
+    let (new_start, new_len) = parse_range(new)?;
+    Some(Hunk {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        section: section.to_string(),
+        lines: Vec::new(),
+    })
+}
+
+fn parse(text: &str) -> Result<Vec<FilePatch>, String> {
+    // Not `lines()`: a `\r` before the newline belongs to CRLF content.
+    let lines: Vec<&str> = text
+        .split_inclusive('\n')
+        .map(|line| line.strip_suffix('\n').unwrap_or(line))
+        .collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx];
+        let number = idx + 1;
+        idx += 1;
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.push(FilePatch {
+                git: Some(git_paths(rest)),
+                ..FilePatch::default()
+            });
+            continue;
+        }
+        if let Some(old) = line.strip_prefix("--- ")
+            && let Some(new) = lines.get(idx).and_then(|l| l.strip_prefix("+++ "))
+        {
+            idx += 1;
+            // A `---` line opens a new file unless it completes a git header.
+            let current = files
+                .last_mut()
+                .filter(|f| f.headers.is_none() && f.hunks.is_empty());
+            let headers = Some((header_path(old), header_path(new)));
+            match current {
+                Some(file) => file.headers = headers,
+                None => files.push(FilePatch {
+                    headers,
+                    ..FilePatch::default()
+                }),
+            }
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            // Commit messages and other preamble before the first file.
+            continue;
+        };
+        if line.starts_with("@@ ") {
+            let mut hunk = parse_hunk_header(line)
+                .ok_or_else(|| parse_error(number, "malformed hunk header"))?;
+            let (mut old_left, mut new_left) = (hunk.old_len, hunk.new_len);
+            while old_left > 0 || new_left > 0 {
+                let Some(&body) = lines.get(idx) else {
+                    return Err(parse_error(idx, "hunk ends early"));
+                };
+                idx += 1;
+                // Some tools strip the space from empty context lines.
+                let kind = body.chars().next().unwrap_or(' ');
+                match kind {
+                    ' ' if old_left > 0 && new_left > 0 => {
+                        old_left -= 1;
+                        new_left -= 1;
+                    }
+                    '-' if old_left > 0 => old_left -= 1,
+                    '+' if new_left > 0 => new_left -= 1,
+                    '\\' => {
+                        if let Some(last) = hunk.lines.last_mut() {
+                            last.no_newline = true;
+                        }
+                        continue;
+                    }
+                    _ => return Err(parse_error(idx, "line does not match the hunk header")),
+                }
+                hunk.lines.push(Line {
+                    kind,
+                    text: body.get(1..).unwrap_or_default().to_string(),
+                    no_newline: false,
+                });
+            }
+            if lines.get(idx).is_some_and(|l| l.starts_with('\\')) {
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.no_newline = true;
+                }
+                idx += 1;
+            }
+            file.hunks.push(hunk);
+        } else if let Some(mode) = line.strip_prefix("new file mode ") {
+            file.created = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            file.deleted = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("old mode ") {
+            file.old_mode = Some(mode.to_string());
+        } else if let Some(mode) = line.strip_prefix("new mode ") {
+            file.new_mode = Some(mode.to_string());
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            let to = file.rename.take().map(|(_, to)| to).unwrap_or_default();
+            file.rename = Some((from.to_string(), to));
+        } else if let Some(to) = line.strip_prefix("rename to ") {
+            let from = file.rename.take().map(|(from, _)| from).unwrap_or_default();
+            file.rename = Some((from, to.to_string()));
+        } else if let Some(index) = line.strip_prefix("index ") {
+            file.index = Some(index.to_string());
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        }
+    }
+    if files.is_empty() {
+        return Err("Patch: no file diffs found".to_string());
+    }
+    Ok(files)
+}
+
+fn strip_path(path: &str, strip: usize) -> Result<String, String> {
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    if parts.len() <= strip {
+        return Err(format!("cannot strip {strip} component(s) from '{path}'"));
+    }
+    Ok(parts[strip..].join("/"))
+}
+
+impl FilePatch {
+    /// Old and new paths relative to the tree root (`None` = no file).
+    /// `rename from`/`rename to` paths carry no `a/`/`b/` prefix.
+    fn paths(&self, strip: usize) -> Result<(Option<String>, Option<String>), String> {
+        if let Some((from, to)) = &self.rename {
+            return Ok((Some(from.clone()), Some(to.clone())));
+        }
+        let (old, new) = match (&self.headers, &self.git) {
+            (Some((old, new)), _) => (old.clone(), new.clone()),
+            (None, Some((old, new))) => (Some(old.clone()), Some(new.clone())),
+            (None, None) => (None, None),
+        };
+        let old = if self.created.is_some() { None } else { old };
+        let new = if self.deleted.is_some() { None } else { new };
+        let strip = |path: Option<String>| path.map(|p| strip_path(&p, strip)).transpose();
+        Ok((strip(old)?, strip(new)?))
+    }
+
+    fn reverse(self) -> Result<FilePatch, String> {
+        if self.binary {
+            return Err("Patch: cannot reverse a binary patch".to_string());
+        }
+        Ok(FilePatch {
+            git: self.git.map(|(old, new)| (new, old)),
+            headers: self.headers.map(|(old, new)| (new, old)),
+            created: self.deleted,
+            deleted: self.created,
+            old_mode: self.new_mode,
+            new_mode: self.old_mode,
+            rename: self.rename.map(|(from, to)| (to, from)),
+            index: self.index.map(|index| {
+                let (hashes, mode) = index.split_once(' ').unwrap_or((&index, ""));
+                let (old, new) = hashes.split_once("..").unwrap_or((hashes, ""));
+                let mode = if mode.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {mode}")
+                };
+                format!("{new}..{old}{mode}")
+            }),
+            binary: false,
+            hunks: self.hunks.into_iter().map(Hunk::reverse).collect(),
+        })
+    }
+
+    fn render(&self, out: &mut String) {
+        if let Some((old, new)) = &self.git {
+            let _ = writeln!(out, "diff --git {old} {new}");
+        }
+        let modes = [
+            ("old mode", &self.old_mode),
+            ("new mode", &self.new_mode),
+            ("deleted file mode", &self.deleted),
+            ("new file mode", &self.created),
+        ];
+        for (label, mode) in modes {
+            if let Some(mode) = mode {
+                let _ = writeln!(out, "{label} {mode}");
+            }
+        }
+        if let Some((from, to)) = &self.rename {
+            let _ = writeln!(out, "rename from {from}\nrename to {to}");
+        }
+        if let Some(index) = &self.index {
+            let _ = writeln!(out, "index {index}");
+        }
+        if let Some((old, new)) = &self.headers {
+            let null = "/dev/null".to_string();
+            let _ = writeln!(out, "--- {}", old.as_ref().unwrap_or(&null));
+            let _ = writeln!(out, "+++ {}", new.as_ref().unwrap_or(&null));
+        }
+        for hunk in &self.hunks {
+            hunk.render(out);
+        }
+    }
+}
+
+fn render_range(start: usize, len: usize) -> String {
+    if len == 1 {
+        start.to_string()
+    } else {
+        format!("{start},{len}")
+    }
+}
+
+impl Hunk {
+    /// Swap sides; within each run of changes the (new) removals still
+    /// come before the additions.
+    fn reverse(self) -> Hunk {
+        let mut lines = Vec::with_capacity(self.lines.len());
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for mut line in self.lines {
+            match line.kind {
+                '+' => {
+                    line.kind = '-';
+                    removed.push(line);
+                }
+                '-' => {
+                    line.kind = '+';
+                    added.push(line);
+                }
+                _ => {
+                    lines.append(&mut removed);
+                    lines.append(&mut added);
+                    lines.push(line);
+                }
+            }
+        }
+        lines.append(&mut removed);
+        lines.append(&mut added);
+        Hunk {
+            old_start: self.new_start,
+            old_len: self.new_len,
+            new_start: self.old_start,
+            new_len: self.old_len,
+            section: self.section,
+            lines,
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "@@ -{} +{} @@{}",
+            render_range(self.old_start, self.old_len),
+            render_range(self.new_start, self.new_len),
+            self.section
+        );
+        for line in &self.lines {
+            let _ = writeln!(out, "{}{}", line.kind, line.text);
+            if line.no_newline {
+                out.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+}
+
+pub fn reverse(text: &str) -> Result<String, String> {
+    let mut out = String::new();
+    for file in parse(text)? {
+        file.reverse()?.render(&mut out);
+    }
+    Ok(out)
+}
+
+/// Where a hunk landed: 1-based line, offset from the header's line and
+/// the context lines ignored to make it fit.
+struct Applied {
+    line: usize,
+    offset: isize,
+    fuzz: usize,
+}
+
+fn body(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+/// Start of `pattern` in `lines[min..]`, searching outward from `expected`.
+fn find_near(lines: &[&str], min: usize, expected: isize, pattern: &[&Line]) -> Option<usize> {
+    let max = lines.len().checked_sub(pattern.len())?;
+    if min > max {
+        return None;
+    }
+    let expected = expected.clamp(min as isize, max as isize) as usize;
+    let matches_at = |pos: usize| {
+        pattern
+            .iter()
+            .enumerate()
+            .all(|(i, line)| body(lines[pos + i]) == line.text)
+    };
+    for distance in 0..=(max - min) {
+        if expected >= min + distance && matches_at(expected - distance) {
+            return Some(expected - distance);
+        }
+        if expected + distance <= max && distance > 0 && matches_at(expected + distance) {
+            return Some(expected + distance);
+        }
+    }
+    None
+}
+
+fn apply_hunks(
+    content: &str,
+    hunks: &[Hunk],
+    max_fuzz: usize,
+) -> Result<(String, Vec<Applied>), String> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(content.len());
+    let mut cursor = 0;
+    let mut shift = 0isize;
+    let mut applied = Vec::new();
+
+    for (number, hunk) in hunks.iter().enumerate() {
+        let base = if hunk.old_len == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let old: Vec<&Line> = hunk.lines.iter().filter(|l| l.kind != '+').collect();
+        let new: Vec<&Line> = hunk.lines.iter().filter(|l| l.kind != '-').collect();
+        let leading = hunk.lines.iter().take_while(|l| l.kind == ' ').count();
+        let trailing = hunk
+            .lines
+            .iter()
+            .rev()
+            .take_while(|l| l.kind == ' ')
+            .count();
+
+        let mut found = None;
+        for fuzz in 0..=max_fuzz {
+            if fuzz > leading.max(trailing) {
+                break;
+            }
+            let (lead, trail) = (fuzz.min(leading), fuzz.min(trailing));
+            if lead + trail > old.len() {
+                break;
+            }
+            let pattern = &old[lead..old.len() - trail];
+            let expected = base as isize + shift + lead as isize;
+            if let Some(pos) = find_near(&lines, cursor, expected, pattern) {
+                found = Some((pos, lead, trail, fuzz));
+                break;
+            }
+        }
+        let Some((pos, lead, trail, fuzz)) = found else {
+            return Err(format!(
+                "hunk #{} (@@ -{} +{} @@) does not apply",
+                number + 1,
+                render_range(hunk.old_start, hunk.old_len),
+                render_range(hunk.new_start, hunk.new_len)
+            ));
+        };
+
+        out.push_str(&lines[cursor..pos].concat());
+        for line in &new[lead..new.len() - trail] {
+            out.push_str(&line.text);
+            if !line.no_newline {
+                out.push('\n');
+            }
+        }
+        cursor = pos + old.len() - lead - trail;
+        let start = pos as isize - lead as isize;
+        shift = start - base as isize;
+        applied.push(Applied {
+            line: (start.max(0) as usize) + 1,
+            offset: shift,
+            fuzz,
+        });
+    }
+    out.push_str(&lines[cursor..].concat());
+    Ok((out, applied))
+}
+
+struct FileResult {
+    action: &'static str,
+    path: Option<String>,
+    old_path: Option<String>,
+    hunks: Vec<Applied>,
+}
+
+/// A file's patched content and permission bits (`None` keeps the bits the
+/// file has now).
+#[derive(Clone)]
+struct Planned {
+    content: String,
+    mode: Option<u32>,
+}
+
+/// Paths changed by the file diffs planned so far: `Some` is written,
+/// `None` removed.
+type Overlay = BTreeMap<String, Option<Planned>>;
+
+fn checked(root: &Path, path: &str) -> Result<PathBuf, String> {
+    match tree::relative_path(path) {
+        Some(rel) if !rel.as_os_str().is_empty() => Ok(root.join(rel)),
+        _ => Err(format!("unsafe path '{path}'")),
+    }
+}
+
+fn read_text(path: &Path, name: &str) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|err| format!("{name}: {err}"))?;
+    String::from_utf8(bytes).map_err(|_| format!("{name}: not a UTF-8 text file"))
+}
+
+/// `path` as the earlier file diffs left it, or `None` when it is missing.
+fn current(root: &Path, overlay: &Overlay, path: &str) -> Result<Option<Planned>, String> {
+    if let Some(planned) = overlay.get(path) {
+        return Ok(planned.clone());
+    }
+    let full = checked(root, path)?;
+    if fs::symlink_metadata(&full).is_err() {
+        return Ok(None);
+    }
+    Ok(Some(Planned {
+        content: read_text(&full, path)?,
+        mode: None,
+    }))
+}
+
+/// Permission bits from a git mode such as `100755`. Symlinks and
+/// submodules have no text content to patch, so they are rejected.
+fn parse_mode(mode: &str) -> Result<u32, String> {
+    let bits = u32::from_str_radix(mode.trim(), 8).map_err(|_| format!("invalid mode '{mode}'"))?;
+    match bits & 0o170000 {
+        0o120000 => Err("symlink patches are not supported".to_string()),
+        0o160000 => Err("submodule patches are not supported".to_string()),
+        _ => Ok(bits & 0o777),
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .ok()
+        .map(|meta| meta.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> Option<u32> {
+    None
+}
+
+fn action(old: &Option<String>, new: &Option<String>) -> Option<&'static str> {
+    match (old, new) {
+        (None, Some(_)) => Some("create"),
+        (Some(_), None) => Some("delete"),
+        (Some(old), Some(new)) if old != new => Some("rename"),
+        (Some(_), Some(_)) => Some("modify"),
+        (None, None) => None,
+    }
+}
+
+/// Check one file diff against the tree as `overlay` leaves it and record
+/// its outcome there.
+fn plan_file(
+    file: &FilePatch,
+    root: &Path,
+    strip: usize,
+    fuzz: usize,
+    overlay: &mut Overlay,
+) -> Result<FileResult, String> {
+    let (old, new) = file.paths(strip)?;
+    let action = action(&old, &new).ok_or("patch names no file")?;
+    let mut result = FileResult {
+        action,
+        path: new.clone().or_else(|| old.clone()),
+        old_path: old.clone(),
+        hunks: Vec::new(),
+    };
+    if file.binary {
+        return Err("binary patches are not supported".to_string());
+    }
+    let mode = file
+        .new_mode
+        .as_deref()
+        .or(file.created.as_deref())
+        .map(parse_mode)
+        .transpose()?;
+
+    let existing = match &old {
+        Some(old) => Some(current(root, overlay, old)?.ok_or_else(|| format!("{old}: not found"))?),
+        None => {
+            let new = new.as_deref().unwrap_or_default();
+            if current(root, overlay, new)?.is_some() {
+                return Err(format!("{new}: already exists"));
+            }
+            None
+        }
+    };
+    let content = existing
+        .as_ref()
+        .map_or("", |existing| existing.content.as_str());
+    let (patched, hunks) = apply_hunks(content, &file.hunks, fuzz)?;
+    result.hunks = hunks;
+
+    match (old, new) {
+        (Some(old), None) => {
+            overlay.insert(old, None);
+        }
+        (old, Some(new)) => {
+            let renamed = old.as_ref().filter(|old| **old != new);
+            if let Some(old) = renamed {
+                let target = checked(root, &new)?;
+                if overlay.get(&new).is_none_or(Option::is_none) && target.exists() {
+                    return Err(format!("{new}: already exists"));
+                }
+                overlay.insert(old.clone(), None);
+            }
+            if patched != content || renamed.is_some() || mode.is_some() {
+                // A renamed file keeps its permissions unless the diff sets them.
+                let kept = existing
+                    .and_then(|existing| existing.mode)
+                    .or_else(|| renamed.and_then(|old| file_mode(&root.join(old))));
+                overlay.insert(
+                    new,
+                    Some(Planned {
+                        content: patched,
+                        mode: mode.or(kept),
+                    }),
+                );
+            }
+        }
+        (None, None) => {}
+    }
+    Ok(result)
+}
+
+/// Print what `result` would do under `--dry-run`; `true` when skipped.
+fn announce(result: &FileResult) -> bool {
+    match (result.action, &result.path, &result.old_path) {
+        ("rename", Some(new), Some(old)) => dry_run_skip(format_args!("rename {old} -> {new}")),
+        ("create", Some(path), _) => dry_run_skip(format_args!("create {path}")),
+        ("delete", Some(path), _) => dry_run_skip(format_args!("delete {path}")),
+        (_, Some(path), _) => dry_run_skip(format_args!(
+            "patch {path} ({} hunk(s))",
+            result.hunks.len()
+        )),
+        _ => false,
+    }
+}
+
+/// One path being replaced: the staged new content (none for a removal)
+/// and, once moved aside, the original.
+struct Swap {
+    path: String,
+    target: PathBuf,
+    staged: Option<PathBuf>,
+    backup: Option<PathBuf>,
+    placed: bool,
+}
+
+fn sibling(target: &Path, label: &str) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    target.with_file_name(unique_name(&format!(".{name}.{label}-")))
+}
+
+/// Write every planned file next to its target, creating missing parent
+/// directories (recorded in `created` so they can be removed again).
+fn stage(
+    root: &Path,
+    overlay: &Overlay,
+    swaps: &mut Vec<Swap>,
+    created: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    for (path, planned) in overlay {
+        let target = root.join(path);
+        let mut swap = Swap {
+            path: path.clone(),
+            target: target.clone(),
+            staged: None,
+            backup: None,
+            placed: false,
+        };
+        if let Some(planned) = planned {
+            let error = |err: std::io::Error| format!("{path}: {err}");
+            if let Some(parent) = target.parent() {
+                let missing: Vec<PathBuf> = parent
+                    .ancestors()
+                    .take_while(|dir| !dir.exists())
+                    .map(Path::to_path_buf)
+                    .collect();
+                fs::create_dir_all(parent).map_err(error)?;
+                created.extend(missing.into_iter().rev());
+            }
+            let staged = sibling(&target, "patch");
+            swap.staged = Some(staged.clone());
+            swaps.push(swap);
+            fs::write(&staged, &planned.content).map_err(error)?;
+            match planned.mode {
+                Some(mode) => set_mode(&staged, mode).map_err(error)?,
+                None => {
+                    if let Ok(existing) = fs::metadata(&target) {
+                        fs::set_permissions(&staged, existing.permissions()).map_err(error)?;
+                    }
+                }
+            }
+        } else {
+            swaps.push(swap);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Move each original aside and the staged file into its place.
+fn swap_in(swaps: &mut [Swap]) -> Result<(), String> {
+    for swap in swaps.iter_mut() {
+        let error = |err: std::io::Error| format!("{}: {err}", swap.path);
+        if fs::symlink_metadata(&swap.target).is_ok() {
+            let backup = sibling(&swap.target, "orig");
+            fs::rename(&swap.target, &backup).map_err(error)?;
+            swap.backup = Some(backup);
+        }
+        if let Some(staged) = &swap.staged {
+            fs::rename(staged, &swap.target).map_err(error)?;
+            swap.placed = true;
+        }
+    }
+    Ok(())
+}
+
+/// Undo `stage` and `swap_in` as far as they got.
+fn roll_back(swaps: &[Swap], created: &[PathBuf]) {
+    for swap in swaps.iter().rev() {
+        if swap.placed {
+            let _ = fs::remove_file(&swap.target);
+        } else if let Some(staged) = &swap.staged {
+            let _ = fs::remove_file(staged);
+        }
+        if let Some(backup) = &swap.backup {
+            let _ = fs::rename(backup, &swap.target);
+        }
+    }
+    for dir in created.iter().rev() {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+/// Bring the tree to the state in `overlay`, or leave it as it was.
+fn commit(root: &Path, overlay: &Overlay) -> Result<(), String> {
+    let mut swaps = Vec::new();
+    let mut created = Vec::new();
+    let outcome = stage(root, overlay, &mut swaps, &mut created).and_then(|()| swap_in(&mut swaps));
+    if outcome.is_err() {
+        roll_back(&swaps, &created);
+        return outcome;
+    }
+    for backup in swaps.iter().filter_map(|swap| swap.backup.as_ref()) {
+        let _ = fs::remove_file(backup);
+    }
+    Ok(())
+}
+
+/// Apply every file in `text` under `root`. Returns
+/// `{"ok", "applied", "files": [...]}`; only malformed patches are `Err`.
+pub fn apply_to_tree(
+    text: &str,
+    root: &Path,
+    strip: usize,
+    fuzz: usize,
+    dry_run: bool,
+) -> Result<Value, String> {
+    let files = parse(text)?;
+    let mut overlay = Overlay::new();
+    let planned: Vec<Result<FileResult, String>> = files
+        .iter()
+        .map(|file| plan_file(file, root, strip, fuzz, &mut overlay))
+        .collect();
+    let mut ok = planned.iter().all(Result::is_ok);
+
+    let mut write_error = None;
+    if ok && !dry_run {
+        let skipped = planned
+            .iter()
+            .flatten()
+            .fold(false, |skipped, result| announce(result) | skipped);
+        if !skipped && let Err(err) = commit(root, &overlay) {
+            write_error = Some(err);
+            ok = false;
+        }
+    }
+
+    let results: Vec<Value> = files
+        .iter()
+        .zip(planned)
+        .map(|(file, planned)| match planned {
+            Ok(result) => Value::map()
+                .with("path", result.path)
+                .with("oldPath", result.old_path)
+                .with("action", result.action)
+                .with("ok", true)
+                .with("error", Value::Null)
+                .with(
+                    "hunks",
+                    Value::List(
+                        result
+                            .hunks
+                            .into_iter()
+                            .map(|hunk| {
+                                Value::map()
+                                    .with("line", hunk.line)
+                                    .with("offset", hunk.offset as f64)
+                                    .with("fuzz", hunk.fuzz)
+                            })
+                            .collect(),
+                    ),
+                ),
+            Err(err) => {
+                let (old, new) = file.paths(strip).unwrap_or_default();
+                Value::map()
+                    .with("path", new.clone().or(old.clone()))
+                    .with("action", action(&old, &new))
+                    .with("oldPath", old)
+                    .with("ok", false)
+                    .with("error", err)
+                    .with("hunks", Value::List(Vec::new()))
+            }
+        })
+        .collect();
+
+    let applied = ok && !dry_run && !crate::stdlib::process::is_dry_run();
+    Ok(Value::map()
+        .with("ok", ok)
+        .with("applied", applied)
+        .with("error", write_error)
+        .with("files", Value::List(results)))
+}
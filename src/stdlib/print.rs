@@ -1,41 +1,28 @@
 #![allow(non_snake_case)]
 
-use chrono::Local;
+use chrono::{Local, SecondsFormat};
 use ruwren::foreign_v2::WrenString;
 use ruwren::{wren_impl, ModuleLibrary, WrenObject};
+use crate::stdlib::style::{Color, Style, parse_spec};
+use crate::stdlib::term::{Stream, paint, use_color};
 use std::fs::OpenOptions;
-use std::io::{Write, stdout};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::RwLock;
+use std::io::{IsTerminal, Write, stdout};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
 
-// ANSI color codes using standard 16-color palette
 const RESET: &str = "\x1b[0m";
-
-// Standard ANSI foreground colors (3X = normal, 9X = bright)
-const FG_BLACK: &str = "\x1b[30m";
-const FG_RED: &str = "\x1b[31m";
-const FG_GREEN: &str = "\x1b[32m";
-const FG_YELLOW: &str = "\x1b[33m";
-const FG_BLUE: &str = "\x1b[34m";
-const FG_MAGENTA: &str = "\x1b[35m";
-const FG_CYAN: &str = "\x1b[36m";
-const FG_WHITE: &str = "\x1b[37m";
-const FG_BRIGHT_BLACK: &str = "\x1b[90m"; // gray
-const FG_BRIGHT_RED: &str = "\x1b[91m";
-const FG_BRIGHT_GREEN: &str = "\x1b[92m";
-const FG_BRIGHT_YELLOW: &str = "\x1b[93m";
-const FG_BRIGHT_BLUE: &str = "\x1b[94m";
-const FG_BRIGHT_MAGENTA: &str = "\x1b[95m";
-const FG_BRIGHT_CYAN: &str = "\x1b[96m";
-const FG_BRIGHT_WHITE: &str = "\x1b[97m";
 const CLEAR_LINE: &str = "\r\x1b[2K";
 
 static LIVE_LINE_ACTIVE: AtomicBool = AtomicBool::new(false);
 static LIVE_BADGE_INDICATOR_FILLED: AtomicBool = AtomicBool::new(false);
 
-fn badge(level: u8, level_name: &str, custom_color: Option<&str>) -> String {
-    let bg = bg_code(level, custom_color);
-    let fg = fg_code(level, custom_color);
+fn badge(level: u8, level_name: &str, custom: Option<&Style>) -> String {
+    if !use_color(Stream::Stdout) {
+        return format!("{} ", level_name);
+    }
+    let color = level_color(level, custom);
+    let bg = color.sgr_for_stdout(true);
+    let fg = color.sgr_for_stdout(false);
     // Format: [black;bg;bold] LEVEL [fg;default-bg]▐[reset] + space
     // Use ▐ (right half block) so the colored part faces the badge (on left)
     // Right half = foreground (badge color), Left half = background (terminal default)
@@ -45,102 +32,257 @@ fn badge(level: u8, level_name: &str, custom_color: Option<&str>) -> String {
     )
 }
 
-fn fg_code(level: u8, custom_color: Option<&str>) -> u8 {
-    if let Some(color_name) = custom_color {
-        ansi_fg_code(color_name)
+/// The colour of a level's badge and key-values: a custom style's
+/// foreground (or background) colour, else the level's own.
+fn level_color(level: u8, custom: Option<&Style>) -> Color {
+    if let Some(style) = custom {
+        style.fg.or(style.bg).unwrap_or(Color::WHITE)
     } else {
         match level {
-            0 => 90, // TRACE - bright black (gray)
-            1 => 34, // DEBUG - blue
-            2 => 32, // INFO - green
-            3 => 93, // WARN - bright yellow
-            4 => 91, // ERROR - bright red
-            _ => 32, // CUSTOM - green
+            0 => Color::GRAY,          // TRACE
+            1 => Color::BLUE,          // DEBUG
+            2 => Color::GREEN,         // INFO
+            3 => Color::BRIGHT_YELLOW, // WARN
+            4 => Color::BRIGHT_RED,    // ERROR
+            _ => Color::GREEN,         // CUSTOM
         }
     }
 }
 
-fn bg_code(level: u8, custom_color: Option<&str>) -> u8 {
-    if let Some(color_name) = custom_color {
-        ansi_bg_code(color_name)
+// ============== Region ==============
+
+/// The lines a `Region` owns at the bottom of the terminal. While one is
+/// drawn the cursor rests at the end of its last line, so anything printed
+/// has to lift it first (`print_above`).
+struct RegionState {
+    id: u64,
+    lines: Vec<String>,
+    /// The live line, drawn below the region's lines while it is shown.
+    live: Option<String>,
+    /// Lines currently on screen.
+    drawn: usize,
+    terminal: bool,
+}
+
+/// Also serves as the output lock: anything that rewrites the bottom of
+/// the terminal holds it, so the spinner thread never draws mid-print.
+static REGION: Mutex<Option<RegionState>> = Mutex::new(None);
+static NEXT_REGION_ID: AtomicU64 = AtomicU64::new(1);
+/// Script output waiting for its newline while the bottom of the terminal
+/// is being redrawn.
+static PENDING_OUTPUT: Mutex<String> = Mutex::new(String::new());
+
+/// `text` on one line, cut to leave the last terminal column free so the
+/// cursor never wraps.
+fn fit_to_width(text: &str, width: Option<usize>) -> String {
+    let text = text.replace(['\n', '\r'], " ");
+    match width {
+        Some(width) => crate::stdlib::layout::truncate(&text, width.saturating_sub(1)),
+        None => text,
+    }
+}
+
+impl RegionState {
+    /// Lines up to the last non-empty one; the rest of the region stays
+    /// free until something is put there.
+    fn shown(&self) -> &[String] {
+        let end = self
+            .lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(0, |idx| idx + 1);
+        &self.lines[..end]
+    }
+
+    fn erase(&mut self, out: &mut impl Write) {
+        if self.drawn == 0 {
+            return;
+        }
+        let _ = write!(out, "{}", CLEAR_LINE);
+        for _ in 1..self.drawn {
+            let _ = write!(out, "\x1b[1A\x1b[2K");
+        }
+        self.drawn = 0;
+    }
+
+    fn draw(&mut self, out: &mut impl Write) {
+        if !self.terminal {
+            return;
+        }
+        let width = crate::stdlib::term::terminal_width();
+        let shown = self.shown().iter().chain(self.live.as_ref());
+        let mut drawn = 0;
+        for line in shown {
+            if drawn > 0 {
+                let _ = writeln!(out);
+            }
+            let _ = write!(out, "{}", paint(&format!("{}{}", fit_to_width(line, width), RESET)));
+            drawn += 1;
+        }
+        self.drawn = drawn;
+    }
+
+    fn redraw(&mut self) {
+        let mut out = stdout();
+        self.erase(&mut out);
+        self.draw(&mut out);
+        let _ = out.flush();
+    }
+}
+
+/// Run `print` with the live line cleared and the region lifted, then draw
+/// the region again below what was printed.
+fn print_above(print: impl FnOnce()) {
+    let mut region = REGION.lock().unwrap();
+    clear_live_line(false);
+    let mut out = stdout();
+    if let Some(region) = region.as_mut() {
+        region.erase(&mut out);
+        region.live = None;
+        let _ = out.flush();
+    }
+    print();
+    if let Some(region) = region.as_mut() {
+        region.draw(&mut out);
+    }
+    let _ = out.flush();
+}
+
+/// Output from `System.print`/`System.write`. While a region or live line
+/// is shown, complete lines go above it and a partial line waits for its
+/// newline.
+pub fn write_script_output(text: String) {
+    let mut pending = PENDING_OUTPUT.lock().unwrap();
+    let lifted = LIVE_LINE_ACTIVE.load(Ordering::SeqCst) || REGION.lock().unwrap().is_some();
+    if !lifted && pending.is_empty() {
+        print!("{}", text);
+        return;
+    }
+    pending.push_str(&text);
+    let Some(end) = pending.rfind('\n') else {
+        return;
+    };
+    let lines: String = pending.drain(..=end).collect();
+    drop(pending);
+    print_above(|| print!("{}", lines));
+}
+
+/// Replace the live line with `text`, cut to the terminal width. While a
+/// region is on screen the live line is drawn below it.
+pub(crate) fn set_live_line(text: &str) {
+    let mut region = REGION.lock().unwrap();
+    if let Some(region) = region.as_mut().filter(|region| region.terminal) {
+        region.live = Some(text.to_string());
+        region.redraw();
+        return;
+    }
+    let width = crate::stdlib::term::terminal_width();
+    let mut out = stdout();
+    let line = paint(&format!("{}{}", fit_to_width(text, width), RESET));
+    let _ = write!(out, "{}{}", CLEAR_LINE, line);
+    let _ = out.flush();
+    LIVE_LINE_ACTIVE.store(true, Ordering::SeqCst);
+}
+
+/// Replace the live line with `text` and keep it as a normal line.
+pub(crate) fn commit_live_line(text: &str) {
+    print_above(|| println!("{}", paint(text)));
+}
+
+pub(crate) fn end_live_line() {
+    let mut region = REGION.lock().unwrap();
+    drop_live_line(&mut region, false);
+}
+
+/// Clear the live line, taking it from below the region when it is there.
+fn drop_live_line(region: &mut Option<RegionState>, newline: bool) {
+    if let Some(region) = region.as_mut()
+        && region.live.take().is_some()
+    {
+        LIVE_BADGE_INDICATOR_FILLED.store(false, Ordering::SeqCst);
+        region.redraw();
+    }
+    clear_live_line(newline);
+}
+
+/// Open a region of `height` lines, finishing any open one.
+pub(crate) fn open_region(height: usize) -> u64 {
+    finish_region();
+    let mut region = REGION.lock().unwrap();
+    clear_live_line(false);
+    let id = NEXT_REGION_ID.fetch_add(1, Ordering::SeqCst);
+    *region = Some(RegionState {
+        id,
+        lines: vec![String::new(); height.max(1)],
+        live: None,
+        drawn: 0,
+        terminal: stdout().is_terminal(),
+    });
+    id
+}
+
+/// Swap in a new set of lines, growing or shrinking the region. Returns
+/// false when the region has been finished.
+pub(crate) fn replace_region(id: u64, lines: Vec<String>) -> bool {
+    with_region(id as f64, |region| region.lines = lines).is_ok()
+}
+
+pub(crate) fn finish_region_id(id: u64) {
+    let open = REGION
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|region| region.id == id);
+    if open {
+        finish_region();
+    }
+}
+
+/// Stop the spinner, clear the live line and leave the region on screen,
+/// then write any unterminated script output. Called when the script
+/// exits so the shell prompt starts on a fresh line.
+pub fn finish_output() {
+    crate::stdlib::progress::stop_spinner();
+    clear_live_line(false);
+    finish_region();
+    let pending = std::mem::take(&mut *PENDING_OUTPUT.lock().unwrap());
+    let mut out = stdout();
+    let _ = write!(out, "{}", pending);
+    let _ = out.flush();
+}
+
+/// Leave the open region's lines on screen as ordinary output.
+fn finish_region() {
+    let Some(mut region) = REGION.lock().unwrap().take() else {
+        return;
+    };
+    let mut out = stdout();
+    if region.live.is_some() {
+        region.erase(&mut out);
+        region.live = None;
+        region.draw(&mut out);
+    }
+    if region.terminal {
+        if region.drawn > 0 {
+            let _ = writeln!(out);
+        }
     } else {
-        match level {
-            0 => 100, // TRACE - bright black (visible gray)
-            1 => 44,  // DEBUG - blue
-            2 => 42,  // INFO - green
-            3 => 103, // WARN - bright yellow background
-            4 => 101, // ERROR - bright red background
-            _ => 42,  // CUSTOM - green
+        for line in region.shown() {
+            let _ = writeln!(out, "{}", line);
         }
     }
+    let _ = out.flush();
 }
 
-fn ansi_color(name: &str) -> &'static str {
-    match name.to_lowercase().as_str() {
-        "black" => FG_BLACK,
-        "red" => FG_RED,
-        "green" => FG_GREEN,
-        "yellow" => FG_YELLOW,
-        "blue" => FG_BLUE,
-        "magenta" => FG_MAGENTA,
-        "cyan" => FG_CYAN,
-        "white" => FG_WHITE,
-        "gray" | "grey" => FG_BRIGHT_BLACK,
-        "bright_black" => FG_BRIGHT_BLACK,
-        "bright_red" => FG_BRIGHT_RED,
-        "bright_green" => FG_BRIGHT_GREEN,
-        "bright_yellow" => FG_BRIGHT_YELLOW,
-        "bright_blue" => FG_BRIGHT_BLUE,
-        "bright_magenta" => FG_BRIGHT_MAGENTA,
-        "bright_cyan" => FG_BRIGHT_CYAN,
-        "bright_white" => FG_BRIGHT_WHITE,
-        _ => FG_WHITE,
-    }
-}
-
-fn ansi_bg_code(name: &str) -> u8 {
-    match name.to_lowercase().as_str() {
-        "black" => 40,
-        "red" => 41,
-        "green" => 42,
-        "yellow" => 43,
-        "blue" => 44,
-        "magenta" => 45,
-        "cyan" => 46,
-        "white" => 47,
-        "gray" | "grey" => 100,
-        "bright_black" => 100,
-        "bright_red" => 101,
-        "bright_green" => 102,
-        "bright_yellow" => 103,
-        "bright_blue" => 104,
-        "bright_magenta" => 105,
-        "bright_cyan" => 106,
-        "bright_white" => 107,
-        _ => 47,
-    }
-}
-
-fn ansi_fg_code(name: &str) -> u8 {
-    match name.to_lowercase().as_str() {
-        "black" => 30,
-        "red" => 31,
-        "green" => 32,
-        "yellow" => 33,
-        "blue" => 34,
-        "magenta" => 35,
-        "cyan" => 36,
-        "white" => 37,
-        "gray" | "grey" => 90,
-        "bright_black" => 90,
-        "bright_red" => 91,
-        "bright_green" => 92,
-        "bright_yellow" => 93,
-        "bright_blue" => 94,
-        "bright_magenta" => 95,
-        "bright_cyan" => 96,
-        "bright_white" => 97,
-        _ => 37,
+fn with_region(id: f64, update: impl FnOnce(&mut RegionState)) -> Result<(), String> {
+    let mut region = REGION.lock().unwrap();
+    match region.as_mut() {
+        Some(state) if state.id as f64 == id => {
+            update(state);
+            state.redraw();
+            Ok(())
+        }
+        _ => Err("Region: already finished".to_string()),
     }
 }
 
@@ -152,7 +294,7 @@ fn clear_live_line(newline: bool) {
     LIVE_BADGE_INDICATOR_FILLED.store(false, Ordering::SeqCst);
     let mut out = stdout();
     if newline {
-        let _ = write!(out, "{}\n", CLEAR_LINE);
+        let _ = writeln!(out, "{}", CLEAR_LINE);
     } else {
         let _ = write!(out, "{}", CLEAR_LINE);
     }
@@ -167,45 +309,84 @@ pub struct PrintInternal;
 #[wren_impl]
 impl PrintInternal {
     fn eprint(&self, msg: WrenString) {
-        clear_live_line(false);
         let msg = msg.into_string().unwrap_or_default();
-        eprintln!("{}", msg);
+        print_above(|| eprintln!("{}", msg));
     }
 
     fn cprint(&self, msg: WrenString) {
-        clear_live_line(false);
         let msg = msg.into_string().unwrap_or_default();
-        println!("{}{}{}", FG_GREEN, msg, RESET);
+        let line = Style::fg(Color::GREEN).apply(&msg);
+        print_above(|| println!("{}", paint(&line)));
     }
 
-    fn cprintColor(&self, msg: WrenString, color: WrenString) {
-        clear_live_line(false);
-        let msg = msg.into_string().unwrap_or_default();
-        let color_name = color.into_string().unwrap_or_default();
-        let color_code = ansi_color(&color_name);
-        println!("{}{}{}", color_code, msg, RESET);
+    fn cprintColor(&self, msg: WrenString, style: WrenString) -> Result<(), String> {
+        let style = parse_spec(style)?;
+        let line = style.apply(&msg.into_string().unwrap_or_default());
+        print_above(|| println!("{}", paint(&line)));
+        Ok(())
     }
 
     fn live(&self, msg: WrenString) {
-        let msg = msg.into_string().unwrap_or_default();
-        let mut out = stdout();
-        let _ = write!(out, "{}{}", CLEAR_LINE, msg);
-        let _ = out.flush();
-        LIVE_LINE_ACTIVE.store(true, Ordering::SeqCst);
+        set_live_line(&msg.into_string().unwrap_or_default());
     }
 
-    fn liveColor(&self, msg: WrenString, color: WrenString) {
-        let msg = msg.into_string().unwrap_or_default();
-        let color_name = color.into_string().unwrap_or_default();
-        let color_code = ansi_color(&color_name);
-        let mut out = stdout();
-        let _ = write!(out, "{}{}{}{}", CLEAR_LINE, color_code, msg, RESET);
-        let _ = out.flush();
-        LIVE_LINE_ACTIVE.store(true, Ordering::SeqCst);
+    fn liveColor(&self, msg: WrenString, style: WrenString) -> Result<(), String> {
+        let style = parse_spec(style)?;
+        set_live_line(&style.apply(&msg.into_string().unwrap_or_default()));
+        Ok(())
     }
 
     fn liveDone(&self) {
-        clear_live_line(true);
+        drop_live_line(&mut REGION.lock().unwrap(), true);
+    }
+
+    /// Open a region of `height` lines, finishing any open one, and
+    /// return its id.
+    fn regionOpen(&self, height: f64) -> f64 {
+        open_region(height.max(1.0) as usize) as f64
+    }
+
+    fn regionSet(&self, id: f64, line: f64, text: WrenString) -> Result<(), String> {
+        let text = text.into_string().unwrap_or_default();
+        with_region(id, |region| {
+            if let Some(slot) = region.lines.get_mut(line as usize) {
+                *slot = text;
+            }
+        })
+    }
+
+    /// Put `text` on the line after the last used one, scrolling the
+    /// oldest line out when the region is full.
+    fn regionPush(&self, id: f64, text: WrenString) -> Result<(), String> {
+        let text = text.into_string().unwrap_or_default();
+        with_region(id, |region| {
+            let used = region.shown().len();
+            if used < region.lines.len() {
+                region.lines[used] = text;
+            } else {
+                region.lines.remove(0);
+                region.lines.push(text);
+            }
+        })
+    }
+
+    fn regionClear(&self, id: f64) -> Result<(), String> {
+        with_region(id, |region| {
+            for line in region.lines.iter_mut() {
+                line.clear();
+            }
+        })
+    }
+
+    fn regionLines(&self, id: f64) -> Vec<String> {
+        match REGION.lock().unwrap().as_ref() {
+            Some(region) if region.id as f64 == id => region.lines.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn regionFinish(&self, id: f64) {
+        finish_region_id(id as u64);
     }
 }
 
@@ -214,7 +395,7 @@ impl PrintInternal {
 #[derive(Clone)]
 struct CustomLevel {
     name: String,
-    color_name: String, // Store color name to derive both fg and bg codes
+    style: Style, // Its colour derives both the badge fg and bg
     priority: u8,       // 0=TRACE, 1=DEBUG, 2=INFO, 3=WARN, 4=ERROR, 5+=custom
 }
 
@@ -225,6 +406,9 @@ struct LogConfig {
     terminal_level: u8, // minimum level for terminal (default: INFO=2)
     file_level: u8,     // minimum level for file (default: DEBUG=1)
     custom_levels: Vec<CustomLevel>,
+    kv_width: Option<usize>, // wider lines get one key=value per line (None: terminal width, 0: never)
+    terminal_format: LogFormat,
+    file_format: LogFormat,
 }
 
 impl Default for LogConfig {
@@ -236,6 +420,9 @@ impl Default for LogConfig {
             terminal_level: 2, // INFO
             file_level: 1,     // DEBUG
             custom_levels: Vec::new(),
+            kv_width: None,
+            terminal_format: LogFormat::Pretty,
+            file_format: LogFormat::Pretty,
         }
     }
 }
@@ -247,6 +434,9 @@ static LOG_CONFIG: RwLock<LogConfig> = RwLock::new(LogConfig {
     terminal_level: 2,
     file_level: 1,
     custom_levels: Vec::new(),
+    kv_width: None,
+    terminal_format: LogFormat::Pretty,
+    file_format: LogFormat::Pretty,
 });
 
 pub fn set_script_dir(dir: String) {
@@ -269,8 +459,7 @@ fn get_log_file_path() -> Option<String> {
         if let Some(ref dir) = config.script_dir {
             let name = config
                 .script_name
-                .as_ref()
-                .map(|n| n.as_str())
+                .as_deref()
                 .unwrap_or("script");
             return Some(format!("{}/wrun_{}.log", dir, name));
         }
@@ -316,7 +505,10 @@ fn should_log_file(level: u8) -> bool {
     }
 }
 
-fn format_kv(kv_str: &str, fg: u8) -> String {
+fn format_kv(kv_str: &str, fg: &str) -> String {
+    if !use_color(Stream::Stdout) {
+        return format_kv_plain(kv_str);
+    }
     if kv_str.is_empty() {
         return String::new();
     }
@@ -344,6 +536,202 @@ fn format_kv(kv_str: &str, fg: u8) -> String {
     }
 }
 
+/// How the key-values of a terminal log line are laid out.
+#[derive(Clone, Copy, PartialEq)]
+enum KvLayout {
+    /// On the message line, unless it would be wider than the kv width or
+    /// a value spans several lines.
+    Auto,
+    Inline,
+    Multiline,
+}
+
+impl KvLayout {
+    fn parse(name: &str) -> KvLayout {
+        match name {
+            "inline" => KvLayout::Inline,
+            "multiline" => KvLayout::Multiline,
+            _ => KvLayout::Auto,
+        }
+    }
+}
+
+const KV_INDENT: &str = "  ";
+
+/// One `key=value` per line under the message, gum-log style. Values with
+/// newlines become a block of `│`-prefixed lines below their key.
+fn format_kv_block(kv_str: &str, fg: &str) -> String {
+    let color = use_color(Stream::Stdout);
+    let dim = |text: &str| {
+        if color {
+            format!("\x1b[0;39;2m{}{}", text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+    let accent = |text: &str| {
+        if color {
+            format!("\x1b[0;{}m{}{}", fg, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+    let mut out = String::new();
+    for pair in kv_str.split('\x00').filter(|p| !p.is_empty()) {
+        let Some((key, value)) = pair.split_once('=') else {
+            out.push_str(&format!("\n{}{}", KV_INDENT, dim(pair)));
+            continue;
+        };
+        if value.contains('\n') {
+            out.push_str(&format!("\n{}{}", KV_INDENT, dim(&format!("{}=", key))));
+            for line in value.trim_end_matches('\n').split('\n') {
+                out.push_str(&format!("\n{0}{0}{1}{2}", KV_INDENT, dim("│ "), accent(line)));
+            }
+        } else {
+            out.push_str(&format!(
+                "\n{}{}{}",
+                KV_INDENT,
+                dim(&format!("{}=", key)),
+                accent(value)
+            ));
+        }
+    }
+    out
+}
+
+/// Whether `line` (the message with inline key-values) should switch to
+/// the multi-line layout.
+fn wants_multiline(layout: KvLayout, kv_str: &str, line: &str) -> bool {
+    match layout {
+        KvLayout::Inline => false,
+        KvLayout::Multiline => true,
+        KvLayout::Auto => {
+            if kv_str.contains('\n') {
+                return true;
+            }
+            let configured = LOG_CONFIG.read().ok().and_then(|config| config.kv_width);
+            let width = match configured {
+                Some(0) => None,
+                Some(width) => Some(width),
+                None => crate::stdlib::term::terminal_width(),
+            };
+            width.is_some_and(|width| crate::stdlib::layout::display_width(line) > width)
+        }
+    }
+}
+
+/// How a log sink writes each record.
+#[derive(Clone, Copy, PartialEq)]
+enum LogFormat {
+    /// Badges and colour on the terminal, `HH:MM DD-MM-YY LEVEL msg k=v` in files.
+    Pretty,
+    Logfmt,
+    Json,
+}
+
+impl LogFormat {
+    fn parse(name: &str) -> Option<LogFormat> {
+        match name.to_lowercase().as_str() {
+            "pretty" => Some(LogFormat::Pretty),
+            "logfmt" => Some(LogFormat::Logfmt),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+fn log_formats() -> (LogFormat, LogFormat) {
+    LOG_CONFIG
+        .read()
+        .map(|config| (config.terminal_format, config.file_format))
+        .unwrap_or((LogFormat::Pretty, LogFormat::Pretty))
+}
+
+/// `\`, `"` and control characters escaped as JSON and logfmt expect.
+fn escape_string(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    escape_string(text, &mut out);
+    out.push('"');
+    out
+}
+
+/// A logfmt value, quoted when it is empty or has spaces, `=`, quotes or
+/// control characters.
+fn logfmt_value(text: &str) -> String {
+    let plain = !text.is_empty()
+        && !text
+            .chars()
+            .any(|c| c == ' ' || c == '=' || c == '"' || c == '\\' || c.is_control());
+    if plain { text.to_string() } else { json_string(text) }
+}
+
+/// A logfmt key: anything that would break parsing becomes `_`.
+fn logfmt_key(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| {
+            if c == ' ' || c == '=' || c == '"' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if key.is_empty() { "_".to_string() } else { key }
+}
+
+/// One log record as a logfmt or JSON line: time (RFC 3339), level,
+/// script, pid, msg and then the key-values, whose values are strings.
+fn format_record(format: LogFormat, time: &str, level_str: &str, msg: &str, kv_str: &str) -> String {
+    let script = LOG_CONFIG
+        .read()
+        .ok()
+        .and_then(|config| config.script_name.clone());
+    let pid = std::process::id();
+    let level = level_str.trim().to_lowercase();
+    let kv: Vec<(&str, &str)> = kv_str
+        .split('\x00')
+        .filter(|p| !p.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .collect();
+    if format == LogFormat::Json {
+        let mut out = format!("{{\"time\":{},\"level\":{}", json_string(time), json_string(&level));
+        if let Some(script) = &script {
+            out.push_str(&format!(",\"script\":{}", json_string(script)));
+        }
+        out.push_str(&format!(",\"pid\":{},\"msg\":{}", pid, json_string(msg)));
+        for (key, value) in kv {
+            out.push_str(&format!(",{}:{}", json_string(key), json_string(value)));
+        }
+        out.push('}');
+        out
+    } else {
+        let mut out = format!("time={} level={}", logfmt_value(time), logfmt_value(&level));
+        if let Some(script) = &script {
+            out.push_str(&format!(" script={}", logfmt_value(script)));
+        }
+        out.push_str(&format!(" pid={} msg={}", pid, logfmt_value(msg)));
+        for (key, value) in kv {
+            out.push_str(&format!(" {}={}", logfmt_key(key), logfmt_value(value)));
+        }
+        out
+    }
+}
+
 fn format_kv_plain(kv_str: &str) -> String {
     if kv_str.is_empty() {
         return String::new();
@@ -357,35 +745,57 @@ fn format_kv_plain(kv_str: &str) -> String {
     }
 }
 
-fn log_message(level: u8, level_str: &str, msg: &str, kv_str: &str, custom_color: Option<&str>) {
-    let time_terminal = Local::now().format("%H:%M").to_string();
-    let time_file = Local::now().format("%H:%M %d-%m-%y").to_string();
-
-    // Terminal output format: LEVEL ▌ HH:MM(dim)  message
-    if should_log_terminal(level) {
-        clear_live_line(false);
-        let badge_str = badge(level, level_str, custom_color);
-        let fg = fg_code(level, custom_color);
-        let kv_formatted = format_kv(kv_str, fg);
+fn log_message(level: u8, level_str: &str, msg: &str, kv_str: &str, custom: Option<&Style>) {
+    log_message_with(level, level_str, msg, kv_str, custom, KvLayout::Auto);
+}
+
+fn log_message_with(
+    level: u8,
+    level_str: &str,
+    msg: &str,
+    kv_str: &str,
+    custom: Option<&Style>,
+    layout: KvLayout,
+) {
+    let now = Local::now();
+    let time_terminal = now.format("%H:%M").to_string();
+    let time_file = now.format("%H:%M %d-%m-%y").to_string();
+    let time_record = now.to_rfc3339_opts(SecondsFormat::Millis, false);
+    let (terminal_format, file_format) = log_formats();
+
+    if should_log_terminal(level) && terminal_format != LogFormat::Pretty {
+        let line = format_record(terminal_format, &time_record, level_str, msg, kv_str);
+        print_above(|| println!("{}", line));
+    } else if should_log_terminal(level) {
+        // Terminal output format: LEVEL ▌ HH:MM(dim)  message
+        let badge_str = badge(level, level_str, custom);
+        let fg = level_color(level, custom).sgr_for_stdout(false);
+        let mut kv_formatted = format_kv(kv_str, &fg);
         // Time is dimmed, then reset, then message, then reset at end
-        println!(
-            "{}\x1b[0;39;2m{}\x1b[0;39m  {}{}\x1b[0m",
-            badge_str, time_terminal, msg, kv_formatted
-        );
+        let head = format!("{}\x1b[0;39;2m{}\x1b[0;39m  {}", badge_str, time_terminal, msg);
+        if wants_multiline(layout, kv_str, &format!("{}{}", head, kv_formatted)) {
+            kv_formatted = format_kv_block(kv_str, &fg);
+        }
+        let line = paint(&format!("{}{}\x1b[0m", head, kv_formatted));
+        print_above(|| println!("{}", line));
     }
 
-    // File output (unchanged format): HH:MM DD-MM-YY LEVEL message
-    if should_log_file(level) {
-        if let Some(file_path) = get_log_file_path() {
+    // File output, pretty: HH:MM DD-MM-YY LEVEL message
+    if should_log_file(level)
+        && let Some(file_path) = get_log_file_path()
+    {
+        let line = if file_format == LogFormat::Pretty {
             let kv_formatted = format_kv_plain(kv_str);
-            let line = format!("{} {} {}{}\n", time_file, level_str, msg, kv_formatted);
-            if let Ok(mut file) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&file_path)
-            {
-                let _ = file.write_all(line.as_bytes());
-            }
+            format!("{} {} {}{}\n", time_file, level_str, msg, kv_formatted)
+        } else {
+            format_record(file_format, &time_record, level_str, msg, kv_str) + "\n"
+        };
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file_path)
+        {
+            let _ = file.write_all(line.as_bytes());
         }
     }
 }
@@ -402,12 +812,12 @@ fn find_custom_level(name: &str) -> Option<CustomLevel> {
     }
 }
 
-fn resolve_level(level_name_str: &str) -> (u8, String, Option<String>) {
+fn resolve_level(level_name_str: &str) -> (u8, String, Option<Style>) {
     if let Some(custom) = find_custom_level(level_name_str) {
         (
             custom.priority,
             format!("{:<5}", custom.name.to_uppercase()),
-            Some(custom.color_name),
+            Some(custom.style),
         )
     } else {
         // Match Log.custom fallback behavior: unknown levels are treated as
@@ -416,8 +826,19 @@ fn resolve_level(level_name_str: &str) -> (u8, String, Option<String>) {
     }
 }
 
-fn log_live_message(level: u8, level_str: &str, msg: &str, kv_str: &str, custom_color: Option<&str>) {
-    if !should_log_terminal(level) {
+/// The style given to `Log.liveColor`, or the level's own when it is empty.
+fn chosen_style(spec: WrenString, resolved: Option<Style>) -> Result<Option<Style>, String> {
+    if spec.bytes().is_empty() {
+        Ok(resolved)
+    } else {
+        parse_spec(spec).map(Some)
+    }
+}
+
+fn log_live_message(level: u8, level_str: &str, msg: &str, kv_str: &str, custom: Option<&Style>) {
+    // A live line is a redrawn status, not a record, so it only shows in
+    // the pretty terminal format.
+    if !should_log_terminal(level) || log_formats().0 != LogFormat::Pretty {
         return;
     }
 
@@ -429,18 +850,14 @@ fn log_live_message(level: u8, level_str: &str, msg: &str, kv_str: &str, custom_
     } else {
         level_str.to_string()
     };
-    let badge_str = badge(level, &live_level_str, custom_color);
-    let fg = fg_code(level, custom_color);
-    let kv_formatted = format_kv(kv_str, fg);
-
-    let mut out = stdout();
-    let _ = write!(
-        out,
-        "{}{}\x1b[0;39;2m{}\x1b[0;39m  \x1b[0;{}m{}\x1b[0;39m{}\x1b[0m",
-        CLEAR_LINE, badge_str, time_terminal, fg, msg, kv_formatted
-    );
-    let _ = out.flush();
-    LIVE_LINE_ACTIVE.store(true, Ordering::SeqCst);
+    let badge_str = badge(level, &live_level_str, custom);
+    let fg = level_color(level, custom).sgr_for_stdout(false);
+    let kv_formatted = format_kv(kv_str, &fg);
+
+    set_live_line(&format!(
+        "{}\x1b[0;39;2m{}\x1b[0;39m  \x1b[0;{}m{}\x1b[0;39m{}\x1b[0m",
+        badge_str, time_terminal, fg, msg, kv_formatted
+    ));
 }
 
 // ============== Log Class ==============
@@ -517,7 +934,7 @@ impl LogInternal {
                 &padded_name,
                 &msg,
                 "",
-                Some(&custom.color_name),
+                Some(&custom.style),
             );
         } else {
             // Fallback: treat as custom above error
@@ -538,7 +955,7 @@ impl LogInternal {
                 &padded_name,
                 &msg,
                 &kv,
-                Some(&custom.color_name),
+                Some(&custom.style),
             );
         } else {
             let padded_name = format!("{:<5}", level_name_str.to_uppercase());
@@ -546,11 +963,28 @@ impl LogInternal {
         }
     }
 
+    /// `Log.<level>(msg, kv, options)`: a built-in or custom level with an
+    /// explicit key-value layout ("auto", "inline" or "multiline").
+    fn entry(&self, level_name_str: WrenString, msg: WrenString, kv: WrenString, layout: WrenString) {
+        let level_name_str = level_name_str.into_string().unwrap_or_default();
+        let msg = msg.into_string().unwrap_or_default();
+        let kv = kv.into_string().unwrap_or_default();
+        let layout = KvLayout::parse(&layout.into_string().unwrap_or_default());
+        let builtin = ["trace", "debug", "info", "warn", "error"]
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(&level_name_str));
+        let (level, level_str, custom) = match builtin {
+            Some(level) => (level as u8, level_name(level as u8).to_string(), None),
+            None => resolve_level(&level_name_str),
+        };
+        log_message_with(level, &level_str, &msg, &kv, custom.as_ref(), layout);
+    }
+
     fn live(&self, level_name_str: WrenString, msg: WrenString) {
         let level_name_str = level_name_str.into_string().unwrap_or_default();
         let msg = msg.into_string().unwrap_or_default();
         let (level, level_str, custom_color) = resolve_level(&level_name_str);
-        log_live_message(level, &level_str, &msg, "", custom_color.as_deref());
+        log_live_message(level, &level_str, &msg, "", custom_color.as_ref());
     }
 
     fn liveKv(&self, level_name_str: WrenString, msg: WrenString, kv: WrenString) {
@@ -558,20 +992,21 @@ impl LogInternal {
         let msg = msg.into_string().unwrap_or_default();
         let kv = kv.into_string().unwrap_or_default();
         let (level, level_str, custom_color) = resolve_level(&level_name_str);
-        log_live_message(level, &level_str, &msg, &kv, custom_color.as_deref());
+        log_live_message(level, &level_str, &msg, &kv, custom_color.as_ref());
     }
 
-    fn liveColor(&self, level_name_str: WrenString, msg: WrenString, color: WrenString) {
+    fn liveColor(
+        &self,
+        level_name_str: WrenString,
+        msg: WrenString,
+        style: WrenString,
+    ) -> Result<(), String> {
         let level_name_str = level_name_str.into_string().unwrap_or_default();
         let msg = msg.into_string().unwrap_or_default();
-        let color = color.into_string().unwrap_or_default();
-        let (level, level_str, resolved_color) = resolve_level(&level_name_str);
-        let chosen_color = if color.is_empty() {
-            resolved_color
-        } else {
-            Some(color)
-        };
-        log_live_message(level, &level_str, &msg, "", chosen_color.as_deref());
+        let (level, level_str, resolved_style) = resolve_level(&level_name_str);
+        let chosen_style = chosen_style(style, resolved_style)?;
+        log_live_message(level, &level_str, &msg, "", chosen_style.as_ref());
+        Ok(())
     }
 
     fn liveColorKv(
@@ -579,19 +1014,15 @@ impl LogInternal {
         level_name_str: WrenString,
         msg: WrenString,
         kv: WrenString,
-        color: WrenString,
-    ) {
+        style: WrenString,
+    ) -> Result<(), String> {
         let level_name_str = level_name_str.into_string().unwrap_or_default();
         let msg = msg.into_string().unwrap_or_default();
         let kv = kv.into_string().unwrap_or_default();
-        let color = color.into_string().unwrap_or_default();
-        let (level, level_str, resolved_color) = resolve_level(&level_name_str);
-        let chosen_color = if color.is_empty() {
-            resolved_color
-        } else {
-            Some(color)
-        };
-        log_live_message(level, &level_str, &msg, &kv, chosen_color.as_deref());
+        let (level, level_str, resolved_style) = resolve_level(&level_name_str);
+        let chosen_style = chosen_style(style, resolved_style)?;
+        log_live_message(level, &level_str, &msg, &kv, chosen_style.as_ref());
+        Ok(())
     }
 
     // Configuration
@@ -610,6 +1041,31 @@ impl LogInternal {
         }
     }
 
+    /// Set the format of the "terminal" or "file" sink, or of both when
+    /// `sink` is empty.
+    fn setFormat(&self, sink: WrenString, format: WrenString) -> Result<(), String> {
+        let sink = sink.into_string().unwrap_or_default();
+        let format = format.into_string().unwrap_or_default();
+        let parsed = LogFormat::parse(&format).ok_or_else(|| {
+            format!("Log: unknown format '{format}', expected \"pretty\", \"logfmt\" or \"json\"")
+        })?;
+        let (terminal, file) = match sink.as_str() {
+            "" => (true, true),
+            "terminal" => (true, false),
+            "file" => (false, true),
+            _ => return Err(format!("Log: unknown sink '{sink}', expected \"terminal\" or \"file\"")),
+        };
+        if let Ok(mut config) = LOG_CONFIG.write() {
+            if terminal {
+                config.terminal_format = parsed;
+            }
+            if file {
+                config.file_format = parsed;
+            }
+        }
+        Ok(())
+    }
+
     fn setFileLevel(&self, level: WrenString) {
         let level = level.into_string().unwrap_or_default();
         let level_num = level_name_to_num(&level);
@@ -618,9 +1074,22 @@ impl LogInternal {
         }
     }
 
-    fn addLevel(&self, name: WrenString, color: WrenString, base_level: WrenString) {
+    /// Lines wider than `width` columns put each key=value on its own line;
+    /// a negative width means the terminal width and 0 turns this off.
+    fn setKvWidth(&self, width: f64) {
+        if let Ok(mut config) = LOG_CONFIG.write() {
+            config.kv_width = (width >= 0.0).then_some(width as usize);
+        }
+    }
+
+    fn addLevel(
+        &self,
+        name: WrenString,
+        style: WrenString,
+        base_level: WrenString,
+    ) -> Result<(), String> {
         let name = name.into_string().unwrap_or_default();
-        let color_name = color.into_string().unwrap_or_default();
+        let style = parse_spec(style)?;
         let base = base_level.into_string().unwrap_or_default();
 
         let priority = if base.is_empty() {
@@ -631,7 +1100,7 @@ impl LogInternal {
 
         let custom = CustomLevel {
             name,
-            color_name,
+            style,
             priority,
         };
 
@@ -642,6 +1111,7 @@ impl LogInternal {
                 .retain(|l| !l.name.eq_ignore_ascii_case(&custom.name));
             config.custom_levels.push(custom);
         }
+        Ok(())
     }
 }
 
@@ -649,6 +1119,9 @@ ruwren::wren_module! {
     mod wrun_print {
         pub crate::stdlib::print::PrintInternal;
         pub crate::stdlib::print::LogInternal;
+        pub crate::stdlib::style::StyleInternal;
+        pub crate::stdlib::progress::ProgressInternal;
+        pub crate::stdlib::layout::LayoutInternal;
     }
 }
 
@@ -1,6 +1,9 @@
+#![allow(non_snake_case)]
+
 use ruwren::foreign_v2::WrenString;
 use ruwren::{ModuleLibrary, WrenObject, wren_impl};
 use std::collections::HashMap;
+use std::io::Write;
 use std::process::{Child, Command, Stdio};
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -29,6 +32,20 @@ pub fn set_dry_run(dry_run: bool) {
     DRY_RUN.store(dry_run, Ordering::SeqCst);
 }
 
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::SeqCst)
+}
+
+/// Under `--dry-run`, print `[dry-run] <action>` and return `true` so the
+/// caller skips the side effect and reports success.
+pub fn dry_run_skip(action: impl std::fmt::Display) -> bool {
+    if !is_dry_run() {
+        return false;
+    }
+    println!("[dry-run] {action}");
+    true
+}
+
 #[derive(WrenObject, Default)]
 pub struct Process;
 
@@ -46,6 +63,8 @@ impl Process {
     }
 
     fn exit(&self, code: f64) {
+        crate::stdlib::print::finish_output();
+        crate::stdlib::cleanup_temp_paths();
         std::process::exit(code as i32);
     }
 
@@ -63,6 +82,19 @@ impl Process {
             Err(_) => 0.0,
         }
     }
+
+    #[wren_impl(getter)]
+    fn dryRun(&self) -> bool {
+        is_dry_run()
+    }
+
+    /// Path of the running `wrun`, so scripts can start a child run of it.
+    #[wren_impl(getter)]
+    fn exe(&self) -> String {
+        std::env::current_exe()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(WrenObject, Default)]
@@ -77,13 +109,13 @@ pub struct Shell {
     last_success: bool,
 }
 
-#[wren_impl]
-impl Shell {
-    fn run(&mut self, command: WrenString) -> bool {
-        let command = command.into_string().unwrap_or_default();
-
+impl ShellClass {
+    fn capture(&mut self, command: &str, input: Option<&[u8]>) -> bool {
         if DRY_RUN.load(Ordering::SeqCst) {
-            println!("[dry-run] {command}");
+            match input {
+                Some(input) => println!("[dry-run] {command} (stdin: {} bytes)", input.len()),
+                None => println!("[dry-run] {command}"),
+            }
             self.last_stdout = String::new();
             self.last_stderr = String::new();
             self.last_exit_code = 0;
@@ -91,18 +123,32 @@ impl Shell {
             return true;
         }
 
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", &command])
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .output()
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
         } else {
-            Command::new("sh")
-                .args(["-c", &command])
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .output()
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", command]);
+            cmd
+        };
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let output = match input {
+            None => cmd.output(),
+            Some(input) => cmd.stdin(Stdio::piped()).spawn().and_then(|mut child| {
+                // Feed stdin from a thread so a command that writes a lot
+                // before reading cannot deadlock against us.
+                let stdin = child.stdin.take();
+                let input = input.to_vec();
+                let writer = std::thread::spawn(move || {
+                    if let Some(mut stdin) = stdin {
+                        let _ = stdin.write_all(&input);
+                    }
+                });
+                let output = child.wait_with_output();
+                let _ = writer.join();
+                output
+            }),
         };
 
         match output {
@@ -122,6 +168,21 @@ impl Shell {
             }
         }
     }
+}
+
+#[wren_impl]
+impl Shell {
+    fn run(&mut self, command: WrenString) -> bool {
+        let command = command.into_string().unwrap_or_default();
+        self.capture(&command, None)
+    }
+
+    /// Like `run`, with `input` (a String; Bytes are converted on the Wren
+    /// side) written to the command's stdin.
+    fn runInput_(&mut self, command: WrenString, input: WrenString) -> bool {
+        let command = command.into_string().unwrap_or_default();
+        self.capture(&command, Some(input.bytes()))
+    }
 
     #[wren_impl(getter)]
     fn stdout(&self) -> String {
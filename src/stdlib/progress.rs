@@ -0,0 +1,464 @@
+//! Progress bars and spinners behind `Progress` and `Spinner` in
+//! `wrun/print`.
+//!
+//! A lone bar or the spinner draws on the live line; a group of bars draws
+//! into a region so each bar keeps its own line. Redraws are throttled.
+//! When stdout is not a terminal, bars print a plain line every few
+//! seconds and once more when they finish.
+
+#![allow(non_snake_case)]
+
+use crate::stdlib::layout::display_width;
+use crate::stdlib::print::{
+    commit_live_line, end_live_line, finish_region_id, open_region, replace_region,
+    set_live_line,
+};
+use crate::stdlib::style::{Color, Style, parse_spec};
+use ruwren::foreign_v2::WrenString;
+use ruwren::{WrenObject, wren_impl};
+use std::collections::HashMap;
+use std::io::{IsTerminal, stdout};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+const PLAIN_INTERVAL: Duration = Duration::from_secs(2);
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static BARS: Mutex<Option<Bars>> = Mutex::new(None);
+static SPINNER: Mutex<Option<Spinner>> = Mutex::new(None);
+
+#[derive(Default)]
+struct Bars {
+    bars: HashMap<u64, Bar>,
+    groups: HashMap<u64, Group>,
+}
+
+struct Bar {
+    group: Option<u64>,
+    total: f64,
+    current: f64,
+    label: String,
+    width: usize,
+    message: String,
+    started: Instant,
+    /// Last redraw, or last plain line without a terminal.
+    drawn_at: Option<Instant>,
+    finished: bool,
+}
+
+struct Group {
+    /// Opened on the first draw, and only on a terminal.
+    region: Option<u64>,
+    bars: Vec<u64>,
+    drawn_at: Option<Instant>,
+    finished: bool,
+}
+
+struct Spinner {
+    id: u64,
+    message: Arc<Mutex<String>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+fn get_bars() -> MutexGuard<'static, Option<Bars>> {
+    let mut guard = BARS.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(Bars::default());
+    }
+    guard
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+fn due(drawn_at: Option<Instant>, interval: Duration) -> bool {
+    drawn_at.is_none_or(|at| at.elapsed() >= interval)
+}
+
+impl Bar {
+    fn elapsed(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    fn rate(&self) -> f64 {
+        let elapsed = self.elapsed();
+        if elapsed > 0.0 {
+            self.current / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    fn eta(&self) -> Option<f64> {
+        let rate = self.rate();
+        if self.finished || self.current >= self.total {
+            Some(0.0)
+        } else if rate > 0.0 {
+            Some((self.total - self.current) / rate)
+        } else {
+            None
+        }
+    }
+
+    fn fraction(&self) -> f64 {
+        (self.current / self.total).clamp(0.0, 1.0)
+    }
+
+    /// `label ████░░░░  42% 42/100 · 12.3/s · ETA 0:05 message`, with the
+    /// label padded to `label_width` so grouped bars line up.
+    fn line(&self, label_width: usize) -> String {
+        let filled = (self.fraction() * self.width as f64).round() as usize;
+        let color = if self.finished {
+            Color::GREEN
+        } else {
+            Color::CYAN
+        };
+        let mut out = String::new();
+        if label_width > 0 || !self.label.is_empty() {
+            let pad = label_width.saturating_sub(display_width(&self.label));
+            out.push_str(&format!("{}{} ", self.label, " ".repeat(pad)));
+        }
+        out.push_str(&format!(
+            "{}{} {:>3}% {}/{}",
+            Style::fg(color).apply(&"█".repeat(filled)),
+            Style::fg(Color::GRAY).apply(&"░".repeat(self.width - filled)),
+            (self.fraction() * 100.0).floor(),
+            format_number(self.current),
+            format_number(self.total),
+        ));
+        out.push_str(&self.timing());
+        if !self.message.is_empty() {
+            out.push(' ');
+            out.push_str(&self.message);
+        }
+        out
+    }
+
+    /// `label 42% (42/100 · 12.3/s · ETA 0:05) message` for logs.
+    fn plain_line(&self) -> String {
+        let mut out = String::new();
+        if !self.label.is_empty() {
+            out.push_str(&self.label);
+            out.push(' ');
+        }
+        out.push_str(&format!(
+            "{}% ({}/{}{})",
+            (self.fraction() * 100.0).floor(),
+            format_number(self.current),
+            format_number(self.total),
+            self.timing(),
+        ));
+        if !self.message.is_empty() {
+            out.push(' ');
+            out.push_str(&self.message);
+        }
+        out
+    }
+
+    fn timing(&self) -> String {
+        let rate = format_number((self.rate() * 10.0).round() / 10.0);
+        if self.finished {
+            return format!(" · {rate}/s · in {}", format_duration(self.elapsed()));
+        }
+        let eta = self.eta().map_or("--:--".to_string(), format_duration);
+        format!(" · {rate}/s · ETA {eta}")
+    }
+}
+
+impl Bars {
+    fn bar(&mut self, id: f64) -> Result<&mut Bar, String> {
+        self.bars
+            .get_mut(&(id as u64))
+            .ok_or_else(|| "Progress: unknown bar".to_string())
+    }
+
+    /// Redraw bar `id` (and its group) unless it was drawn too recently.
+    /// Finishing always draws.
+    fn draw(&mut self, id: u64, force: bool) {
+        let terminal = stdout().is_terminal();
+        let Some(bar) = self.bars.get_mut(&id) else {
+            return;
+        };
+        if let Some(group) = bar.group {
+            if terminal {
+                self.draw_group(group, force);
+                return;
+            }
+        } else if terminal {
+            if force {
+                commit_live_line(&bar.line(0));
+            } else if due(bar.drawn_at, REDRAW_INTERVAL) {
+                set_live_line(&bar.line(0));
+                bar.drawn_at = Some(Instant::now());
+            }
+            return;
+        }
+        // Without a terminal the first plain line waits a full interval, so
+        // quick jobs only log their result.
+        let drawn_at = *bar.drawn_at.get_or_insert(bar.started);
+        if force || drawn_at.elapsed() >= PLAIN_INTERVAL {
+            commit_live_line(&bar.plain_line());
+            bar.drawn_at = Some(Instant::now());
+        }
+    }
+
+    fn draw_group(&mut self, id: u64, force: bool) {
+        let Some(group) = self.groups.get_mut(&id) else {
+            return;
+        };
+        if group.finished || !(force || due(group.drawn_at, REDRAW_INTERVAL)) {
+            return;
+        }
+        let bars: Vec<&Bar> = group
+            .bars
+            .iter()
+            .filter_map(|id| self.bars.get(id))
+            .collect();
+        let label_width = bars
+            .iter()
+            .map(|bar| display_width(&bar.label))
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<String> = bars.iter().map(|bar| bar.line(label_width)).collect();
+        let region = *group.region.get_or_insert_with(|| open_region(lines.len()));
+        if !replace_region(region, lines) {
+            // Another region took over the bottom of the terminal.
+            group.finished = true;
+        }
+        group.drawn_at = Some(Instant::now());
+    }
+
+    fn finish(&mut self, id: u64) {
+        let Some(bar) = self.bars.get_mut(&id) else {
+            return;
+        };
+        if bar.finished {
+            return;
+        }
+        bar.finished = true;
+        self.draw(id, true);
+    }
+}
+
+/// Stop the spinner thread and clear its line; the final message, if any,
+/// is up to the caller.
+pub fn stop_spinner() -> bool {
+    let Some(mut spinner) = SPINNER.lock().unwrap().take() else {
+        return false;
+    };
+    spinner.stop.store(true, Ordering::SeqCst);
+    if let Some(thread) = spinner.thread.take() {
+        thread.thread().unpark();
+        let _ = thread.join();
+        end_live_line();
+    }
+    true
+}
+
+fn spin(message: Arc<Mutex<String>>, stop: Arc<AtomicBool>) {
+    for frame in SPINNER_FRAMES.iter().cycle() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        let text = message.lock().unwrap().clone();
+        set_live_line(&format!("{} {text}", Style::fg(Color::CYAN).apply(frame)));
+        std::thread::park_timeout(SPINNER_INTERVAL);
+    }
+}
+
+#[derive(WrenObject, Default)]
+pub struct ProgressInternal;
+
+#[wren_impl]
+impl ProgressInternal {
+    fn barNew(&self, total: f64, label: WrenString, width: f64, group: f64) -> f64 {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let group = (group > 0.0).then_some(group as u64);
+        let mut guard = get_bars();
+        let bars = guard.as_mut().unwrap();
+        if let Some(group) = group.and_then(|group| bars.groups.get_mut(&group)) {
+            group.bars.push(id);
+        }
+        bars.bars.insert(
+            id,
+            Bar {
+                group,
+                total,
+                current: 0.0,
+                label: label.into_string().unwrap_or_default(),
+                width: width.max(1.0) as usize,
+                message: String::new(),
+                started: Instant::now(),
+                drawn_at: None,
+                finished: false,
+            },
+        );
+        bars.draw(id, false);
+        id as f64
+    }
+
+    /// Move bar `id` to `current`; reaching the total finishes it. Updates
+    /// to a finished bar are ignored.
+    fn barSet(&self, id: f64, current: f64) -> Result<(), String> {
+        let mut guard = get_bars();
+        let bars = guard.as_mut().unwrap();
+        let bar = bars.bar(id)?;
+        if bar.finished {
+            return Ok(());
+        }
+        bar.current = current.max(0.0);
+        if bar.current >= bar.total {
+            bars.finish(id as u64);
+        } else {
+            bars.draw(id as u64, false);
+        }
+        Ok(())
+    }
+
+    fn barMessage(&self, id: f64, text: WrenString) -> Result<(), String> {
+        let mut guard = get_bars();
+        let bars = guard.as_mut().unwrap();
+        let bar = bars.bar(id)?;
+        if !bar.finished {
+            bar.message = text.into_string().unwrap_or_default();
+            bars.draw(id as u64, false);
+        }
+        Ok(())
+    }
+
+    fn barFinish(&self, id: f64) -> Result<(), String> {
+        let mut guard = get_bars();
+        let bars = guard.as_mut().unwrap();
+        bars.bar(id)?;
+        bars.finish(id as u64);
+        Ok(())
+    }
+
+    fn barCurrent(&self, id: f64) -> Result<f64, String> {
+        Ok(get_bars().as_mut().unwrap().bar(id)?.current)
+    }
+
+    fn barRate(&self, id: f64) -> Result<f64, String> {
+        Ok(get_bars().as_mut().unwrap().bar(id)?.rate())
+    }
+
+    fn barEta(&self, id: f64) -> Result<Option<f64>, String> {
+        Ok(get_bars().as_mut().unwrap().bar(id)?.eta())
+    }
+
+    fn barElapsed(&self, id: f64) -> Result<f64, String> {
+        Ok(get_bars().as_mut().unwrap().bar(id)?.elapsed())
+    }
+
+    fn barFinished(&self, id: f64) -> Result<bool, String> {
+        Ok(get_bars().as_mut().unwrap().bar(id)?.finished)
+    }
+
+    fn groupNew(&self) -> f64 {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        get_bars().as_mut().unwrap().groups.insert(
+            id,
+            Group {
+                region: None,
+                bars: Vec::new(),
+                drawn_at: None,
+                finished: false,
+            },
+        );
+        id as f64
+    }
+
+    /// Finish every bar in group `id` and leave the group's lines on screen.
+    fn groupFinish(&self, id: f64) {
+        let mut guard = get_bars();
+        let bars = guard.as_mut().unwrap();
+        let Some(group) = bars.groups.get(&(id as u64)) else {
+            return;
+        };
+        for bar in group.bars.clone() {
+            bars.finish(bar);
+        }
+        if let Some(group) = bars.groups.get_mut(&(id as u64)) {
+            group.finished = true;
+            if let Some(region) = group.region {
+                finish_region_id(region);
+            }
+        }
+    }
+
+    /// Start the spinner, replacing any running one. Without a terminal the
+    /// message is printed once instead of animated.
+    fn spinnerStart(&self, text: WrenString) -> f64 {
+        stop_spinner();
+        let text = text.into_string().unwrap_or_default();
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let message = Arc::new(Mutex::new(text.clone()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = if stdout().is_terminal() {
+            let (message, stop) = (message.clone(), stop.clone());
+            Some(std::thread::spawn(move || spin(message, stop)))
+        } else {
+            commit_live_line(&format!("- {text}"));
+            None
+        };
+        *SPINNER.lock().unwrap() = Some(Spinner {
+            id,
+            message,
+            stop,
+            thread,
+        });
+        id as f64
+    }
+
+    fn spinnerMessage(&self, id: f64, text: WrenString) -> Result<(), String> {
+        match SPINNER.lock().unwrap().as_ref() {
+            Some(spinner) if spinner.id as f64 == id => {
+                *spinner.message.lock().unwrap() = text.into_string().unwrap_or_default();
+                Ok(())
+            }
+            _ => Err("Spinner: already stopped".to_string()),
+        }
+    }
+
+    /// Stop spinner `id`, then print `symbol text` in `color` unless the
+    /// symbol is empty. Stopping an already stopped spinner does nothing.
+    fn spinnerStop(&self, id: f64, symbol: WrenString, color: WrenString, text: WrenString) {
+        let current = SPINNER
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|spinner| spinner.id as f64 == id);
+        if !current || !stop_spinner() {
+            return;
+        }
+        let symbol = symbol.into_string().unwrap_or_default();
+        if symbol.is_empty() {
+            return;
+        }
+        let style = parse_spec(color).unwrap_or_default();
+        let text = text.into_string().unwrap_or_default();
+        commit_live_line(&format!("{} {text}", style.apply(&symbol)));
+    }
+}
@@ -0,0 +1,451 @@
+//! Interactive prompts behind `wrun/prompt`.
+//!
+//! Questions are drawn on stderr so stdout stays clean for piping. `select`
+//! and `multiSelect` read keys in raw mode on unix; elsewhere they fall back
+//! to typing an option number. Whether to prompt at all is decided on the
+//! Wren side, which answers with the defaults when stdin is not a terminal.
+
+#![allow(non_snake_case)]
+
+use crate::stdlib::print::end_live_line;
+use crate::stdlib::term::{Stream, use_color};
+use ruwren::foreign_v2::WrenString;
+use ruwren::{ModuleLibrary, WrenObject, wren_impl};
+use std::io::{IsTerminal, Read, Write, stderr, stdin};
+
+const CANCELLED: &str = "Prompt: cancelled";
+
+fn styled(code: &str, text: &str) -> String {
+    if use_color(Stream::Stderr) {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+fn question_line(question: &str, hint: &str) -> String {
+    let hint = if hint.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", styled("90", hint))
+    };
+    format!(
+        "{} {}{hint} {} ",
+        styled("1;32", "?"),
+        styled("1", question),
+        styled("90", "›")
+    )
+}
+
+/// The line a finished prompt leaves behind.
+fn print_answer(question: &str, answer: &str) {
+    eprintln!("{}{}", question_line(question, ""), styled("36", answer));
+}
+
+/// One line from stdin without its newline; end of input cancels.
+fn read_line() -> Result<String, String> {
+    let mut line = String::new();
+    match stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => Err(CANCELLED.to_string()),
+        Ok(_) => Ok(line.trim_end_matches(['\n', '\r']).to_string()),
+    }
+}
+
+/// A line typed without echo. It is read key by key so Ctrl-C cancels the
+/// prompt (and restores the terminal) rather than killing wrun with echo
+/// still off. Backspace and Ctrl-U edit the line.
+fn read_hidden() -> Result<String, String> {
+    #[cfg(unix)]
+    if let Some(_raw) = RawMode::keys() {
+        let mut input = stdin().lock();
+        let mut line: Vec<u8> = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            if !matches!(input.read(&mut byte), Ok(1)) {
+                return Err(CANCELLED.to_string());
+            }
+            match byte[0] {
+                b'\r' | b'\n' => break,
+                3 => return Err(CANCELLED.to_string()),
+                4 if line.is_empty() => return Err(CANCELLED.to_string()),
+                0x7f | 8 => {
+                    // Drop the last character with its UTF-8 continuation bytes.
+                    while let Some(last) = line.pop() {
+                        if last & 0xc0 != 0x80 {
+                            break;
+                        }
+                    }
+                }
+                0x15 => line.clear(),
+                other => line.push(other),
+            }
+        }
+        return Ok(String::from_utf8_lossy(&line).into_owned());
+    }
+    read_line()
+}
+
+/// Terminal input without line buffering or echo, restored on drop.
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    /// Single key presses, unechoed, with Ctrl-C read as a key.
+    fn keys() -> Option<Self> {
+        Self::enable(|raw| {
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+        })
+    }
+
+    fn enable(configure: impl FnOnce(&mut libc::termios)) -> Option<Self> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return None;
+        }
+        let mut raw = original;
+        configure(&mut raw);
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return None;
+        }
+        Some(RawMode { original })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Key {
+    Up,
+    Down,
+    Space,
+    Enter,
+    Char(char),
+    Cancel,
+}
+
+fn read_key() -> Key {
+    let mut input = stdin().lock();
+    let mut byte = [0u8; 1];
+    let mut next = || match input.read(&mut byte) {
+        Ok(1) => Some(byte[0]),
+        _ => None,
+    };
+    match next() {
+        None | Some(3) | Some(4) => Key::Cancel,
+        Some(b'\r') | Some(b'\n') => Key::Enter,
+        Some(b' ') => Key::Space,
+        Some(0x1b) => match (next(), next()) {
+            (Some(b'['), Some(b'A')) => Key::Up,
+            (Some(b'['), Some(b'B')) => Key::Down,
+            _ => Key::Char('\x1b'),
+        },
+        Some(b'k') => Key::Up,
+        Some(b'j') => Key::Down,
+        Some(byte) => Key::Char(byte as char),
+    }
+}
+
+/// Arrow-key list shared by `select` and `multiSelect`. `checked` is
+/// `None` for a single choice.
+struct Menu<'a> {
+    question: &'a str,
+    labels: &'a [&'a str],
+    cursor: usize,
+    checked: Option<Vec<bool>>,
+    drawn: usize,
+}
+
+impl Menu<'_> {
+    fn draw(&mut self) {
+        let mut out = stderr();
+        if self.drawn > 0 {
+            let _ = write!(out, "\x1b[{}A\r\x1b[J", self.drawn);
+        }
+        let hint = if self.checked.is_some() {
+            "(↑/↓ move, space toggles, a toggles all, enter confirms)"
+        } else {
+            "(↑/↓ move, enter selects)"
+        };
+        let _ = writeln!(out, "{}", question_line(self.question, hint));
+        for (idx, label) in self.labels.iter().enumerate() {
+            let pointer = if idx == self.cursor {
+                styled("36", "❯")
+            } else {
+                " ".to_string()
+            };
+            let mark = match &self.checked {
+                Some(checked) if checked[idx] => format!("{} ", styled("32", "◉")),
+                Some(_) => "◯ ".to_string(),
+                None => String::new(),
+            };
+            let label = if idx == self.cursor {
+                styled("36", label)
+            } else {
+                label.to_string()
+            };
+            let _ = writeln!(out, "{pointer} {mark}{label}");
+        }
+        self.drawn = self.labels.len() + 1;
+        let _ = out.flush();
+    }
+
+    fn erase(&mut self) {
+        let mut out = stderr();
+        let _ = write!(out, "\x1b[{}A\r\x1b[J\x1b[?25h", self.drawn);
+        let _ = out.flush();
+        self.drawn = 0;
+    }
+
+    /// Run the menu until enter; `false` when cancelled.
+    fn run(&mut self) -> bool {
+        let _ = write!(stderr(), "\x1b[?25l");
+        self.draw();
+        loop {
+            let count = self.labels.len();
+            match read_key() {
+                Key::Up => self.cursor = (self.cursor + count - 1) % count,
+                Key::Down => self.cursor = (self.cursor + 1) % count,
+                Key::Space => {
+                    if let Some(checked) = &mut self.checked {
+                        checked[self.cursor] = !checked[self.cursor];
+                    }
+                }
+                Key::Char('a') => {
+                    if let Some(checked) = &mut self.checked {
+                        let all = checked.iter().all(|&on| on);
+                        checked.iter_mut().for_each(|on| *on = !all);
+                    }
+                }
+                Key::Enter => {
+                    self.erase();
+                    return true;
+                }
+                Key::Cancel => {
+                    self.erase();
+                    return false;
+                }
+                Key::Char(_) => {}
+            }
+            self.draw();
+        }
+    }
+}
+
+/// Fallback without raw mode: a numbered list and a typed answer.
+fn ask_numbers(question: &str, labels: &[&str], hint: &str) -> Result<String, String> {
+    for (idx, label) in labels.iter().enumerate() {
+        eprintln!("  {}) {label}", idx + 1);
+    }
+    eprint!("{}", question_line(question, hint));
+    read_line()
+}
+
+fn parse_number(text: &str, count: usize) -> Option<usize> {
+    text.trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|number| (1..=count).contains(number))
+        .map(|number| number - 1)
+}
+
+fn select(question: &str, labels: &[&str], default: usize) -> Result<usize, String> {
+    #[cfg(unix)]
+    if let Some(_raw) = RawMode::keys() {
+        let mut menu = Menu {
+            question,
+            labels,
+            cursor: default,
+            checked: None,
+            drawn: 0,
+        };
+        return if menu.run() {
+            Ok(menu.cursor)
+        } else {
+            Err(CANCELLED.to_string())
+        };
+    }
+    loop {
+        let answer = ask_numbers(question, labels, &format!("[{}]", default + 1))?;
+        if answer.trim().is_empty() {
+            return Ok(default);
+        }
+        if let Some(idx) = parse_number(&answer, labels.len()) {
+            return Ok(idx);
+        }
+    }
+}
+
+fn multi_select(question: &str, labels: &[&str], checked: Vec<bool>) -> Result<Vec<bool>, String> {
+    #[cfg(unix)]
+    if let Some(_raw) = RawMode::keys() {
+        let mut menu = Menu {
+            question,
+            labels,
+            cursor: 0,
+            checked: Some(checked),
+            drawn: 0,
+        };
+        return if menu.run() {
+            Ok(menu.checked.unwrap_or_default())
+        } else {
+            Err(CANCELLED.to_string())
+        };
+    }
+    'ask: loop {
+        let answer = ask_numbers(question, labels, "(numbers separated by spaces or commas)")?;
+        if answer.trim().is_empty() {
+            return Ok(checked);
+        }
+        let mut chosen = vec![false; labels.len()];
+        for part in answer.split([' ', ',']).filter(|part| !part.is_empty()) {
+            match parse_number(part, labels.len()) {
+                Some(idx) => chosen[idx] = true,
+                None => continue 'ask,
+            }
+        }
+        return Ok(chosen);
+    }
+}
+
+fn labels(joined: &str) -> Vec<&str> {
+    joined.split('\x00').collect()
+}
+
+#[derive(WrenObject, Default)]
+pub struct PromptInternal;
+
+#[wren_impl]
+impl PromptInternal {
+    /// Prompts need a terminal on stdin to read from and on stderr to draw.
+    fn interactive(&self) -> bool {
+        stdin().is_terminal() && stderr().is_terminal()
+    }
+
+    /// Record an answer taken without asking, so logs show what was used.
+    fn answered(&self, question: WrenString, answer: WrenString) {
+        print_answer(
+            &question.into_string().unwrap_or_default(),
+            &answer.into_string().unwrap_or_default(),
+        );
+    }
+
+    fn input(&self, question: WrenString, default: WrenString) -> Result<String, String> {
+        end_live_line();
+        let question = question.into_string().unwrap_or_default();
+        let default = default.into_string().unwrap_or_default();
+        let hint = if default.is_empty() {
+            String::new()
+        } else {
+            format!("({default})")
+        };
+        eprint!("{}", question_line(&question, &hint));
+        let answer = read_line()?;
+        let answer = if answer.is_empty() { default } else { answer };
+        eprint!("\x1b[1A\r\x1b[2K");
+        print_answer(&question, &answer);
+        Ok(answer)
+    }
+
+    fn confirm(&self, question: WrenString, default: bool) -> Result<bool, String> {
+        end_live_line();
+        let question = question.into_string().unwrap_or_default();
+        let hint = if default { "(Y/n)" } else { "(y/N)" };
+        loop {
+            eprint!("{}", question_line(&question, hint));
+            let answer = match read_line()?.trim().to_lowercase().as_str() {
+                "" => default,
+                "y" | "yes" => true,
+                "n" | "no" => false,
+                _ => continue,
+            };
+            eprint!("\x1b[1A\r\x1b[2K");
+            print_answer(&question, if answer { "yes" } else { "no" });
+            return Ok(answer);
+        }
+    }
+
+    fn password(&self, question: WrenString) -> Result<String, String> {
+        end_live_line();
+        let question = question.into_string().unwrap_or_default();
+        eprint!("{}", question_line(&question, ""));
+        let answer = read_hidden();
+        eprintln!();
+        let answer = answer?;
+        eprint!("\x1b[1A\r\x1b[2K");
+        // Fixed, so the mask does not give away the length.
+        print_answer(&question, "********");
+        Ok(answer)
+    }
+
+    /// `labels` joined by `\x00`; returns the chosen index.
+    fn select(
+        &self,
+        question: WrenString,
+        labels_joined: WrenString,
+        default: f64,
+    ) -> Result<f64, String> {
+        end_live_line();
+        let question = question.into_string().unwrap_or_default();
+        let joined = labels_joined.into_string().unwrap_or_default();
+        let labels = labels(&joined);
+        let default = (default.max(0.0) as usize).min(labels.len() - 1);
+        let idx = select(&question, &labels, default)?;
+        print_answer(&question, labels[idx]);
+        Ok(idx as f64)
+    }
+
+    /// `labels` and pre-checked indexes joined by `\x00`; returns the
+    /// checked indexes.
+    fn multiSelect(
+        &self,
+        question: WrenString,
+        labels_joined: WrenString,
+        checked_joined: WrenString,
+    ) -> Result<Vec<f64>, String> {
+        end_live_line();
+        let question = question.into_string().unwrap_or_default();
+        let joined = labels_joined.into_string().unwrap_or_default();
+        let labels = labels(&joined);
+        let mut checked = vec![false; labels.len()];
+        for idx in checked_joined
+            .into_string()
+            .unwrap_or_default()
+            .split('\x00')
+        {
+            if let Some(on) = idx
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| checked.get_mut(idx))
+            {
+                *on = true;
+            }
+        }
+        let checked = multi_select(&question, &labels, checked)?;
+        let chosen: Vec<usize> = (0..labels.len()).filter(|&idx| checked[idx]).collect();
+        let names: Vec<&str> = chosen.iter().map(|&idx| labels[idx]).collect();
+        print_answer(&question, &names.join(", "));
+        Ok(chosen.into_iter().map(|idx| idx as f64).collect())
+    }
+}
+
+ruwren::wren_module! {
+    mod wrun_prompt {
+        pub crate::stdlib::prompt::PromptInternal;
+    }
+}
+
+pub fn publish_module(lib: &mut ModuleLibrary) {
+    wrun_prompt::publish_module(lib);
+}
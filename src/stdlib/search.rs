@@ -0,0 +1,287 @@
+//! `wrun/search`: recursive text search and search/replace across files.
+//!
+//! Files are walked with the `ignore` crate (so `.gitignore`, `.ignore` and
+//! hidden files are skipped like ripgrep does) and searched on its worker
+//! threads; results are sorted by path afterwards so output is stable.
+
+#![allow(non_snake_case)]
+
+use crate::stdlib::value::Value;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{WalkBuilder, WalkState};
+use regex::{NoExpand, Regex, RegexBuilder};
+use ruwren::foreign_v2::WrenString;
+use ruwren::{ModuleLibrary, WrenObject, wren_impl};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Files with a NUL byte in their first 8 KB are treated as binary.
+const BINARY_PROBE: usize = 8 * 1024;
+
+struct Walk {
+    roots: Vec<String>,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    hidden: bool,
+    ignore: bool,
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split('\x00')
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn build_globs(patterns: &[&str]) -> Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|err| format!("Search: invalid glob: {err}"))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|err| format!("Search: invalid glob: {err}"))
+}
+
+impl Walk {
+    /// `globs` is `\x00`-joined; a leading `!` turns a glob into an exclude.
+    fn new(paths: &str, globs: &str, hidden: bool, ignore: bool) -> Result<Walk, String> {
+        let roots = split_list(paths);
+        if roots.is_empty() {
+            return Err("Search: no paths given".to_string());
+        }
+        if let Some(missing) = roots.iter().find(|root| !Path::new(root).exists()) {
+            return Err(format!("Search: {missing}: no such file or directory"));
+        }
+        let globs = split_list(globs);
+        let include: Vec<&str> = globs
+            .iter()
+            .filter(|g| !g.starts_with('!'))
+            .map(|g| g.as_str())
+            .collect();
+        let exclude: Vec<&str> = globs.iter().filter_map(|g| g.strip_prefix('!')).collect();
+        Ok(Walk {
+            roots,
+            include: build_globs(&include)?,
+            exclude: build_globs(&exclude)?,
+            hidden,
+            ignore,
+        })
+    }
+
+    /// Globs match the path relative to its search root or the file name,
+    /// like `Dir.copy`'s `exclude`.
+    fn selects(&self, root: &Path, path: &Path) -> bool {
+        let rel = path.strip_prefix(root).unwrap_or(path);
+        let matches = |set: &GlobSet| {
+            set.is_match(rel) || path.file_name().is_some_and(|name| set.is_match(name))
+        };
+        self.include.as_ref().is_none_or(matches) && !self.exclude.as_ref().is_some_and(matches)
+    }
+
+    /// Run `visit` on every selected file in parallel and return the
+    /// results sorted by path.
+    fn run<T: Send>(&self, visit: impl Fn(&Path) -> Option<T> + Sync) -> Vec<(String, T)> {
+        let results = Mutex::new(Vec::new());
+        for root in &self.roots {
+            let root_path = Path::new(root);
+            let mut builder = WalkBuilder::new(root);
+            builder
+                .standard_filters(self.ignore)
+                .hidden(!self.hidden)
+                .require_git(false);
+            builder.build_parallel().run(|| {
+                Box::new(|entry| {
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+                    let path = entry.path();
+                    let is_file = entry.file_type().is_some_and(|t| t.is_file());
+                    if is_file
+                        && self.selects(root_path, path)
+                        && let Some(result) = visit(path)
+                    {
+                        let name = path.to_string_lossy().replace('\\', "/");
+                        results.lock().unwrap().push((name, result));
+                    }
+                    WalkState::Continue
+                })
+            });
+        }
+        let mut results = results.into_inner().unwrap();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results.dedup_by(|a, b| a.0 == b.0);
+        results
+    }
+}
+
+fn build_regex(pattern: &str, regex: bool, ignore_case: bool) -> Result<Regex, String> {
+    let pattern = if regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|err| format!("Search: invalid pattern: {err}"))
+}
+
+/// File content as text, or `None` for unreadable and binary files.
+fn read_text(path: &Path) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    let probe = &bytes[..bytes.len().min(BINARY_PROBE)];
+    (!probe.contains(&0)).then_some(bytes)
+}
+
+struct Match {
+    line: usize,
+    column: usize,
+    text: String,
+    found: String,
+    context: Option<(Vec<String>, Vec<String>)>,
+}
+
+impl Match {
+    fn into_value(self, path: &str) -> Value {
+        let value = Value::map()
+            .with("path", path)
+            .with("line", self.line)
+            .with("column", self.column)
+            .with("text", self.text)
+            .with("match", self.found);
+        match self.context {
+            Some((before, after)) => value.with("before", before).with("after", after),
+            None => value,
+        }
+    }
+}
+
+fn grep_file(path: &Path, regex: &Regex, context: usize) -> Option<Vec<Match>> {
+    let bytes = read_text(path)?;
+    let text = String::from_utf8_lossy(&bytes);
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let owned = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect();
+    let mut matches = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let Some(found) = regex.find(line) else {
+            continue;
+        };
+        matches.push(Match {
+            line: idx + 1,
+            column: line[..found.start()].chars().count() + 1,
+            text: line.to_string(),
+            found: found.as_str().to_string(),
+            context: (context > 0).then(|| {
+                let before = &lines[idx.saturating_sub(context)..idx];
+                let after = &lines[idx + 1..(idx + 1 + context).min(lines.len())];
+                (owned(before), owned(after))
+            }),
+        });
+    }
+    (!matches.is_empty()).then_some(matches)
+}
+
+#[derive(WrenObject, Default)]
+pub struct Search;
+
+#[wren_impl]
+impl Search {
+    /// One map per matching line: `path`, `line`, `column` (1-based, in
+    /// characters), `text`, `match`, plus `before`/`after` with `context`.
+    #[allow(clippy::too_many_arguments)]
+    fn grep_(
+        &self,
+        pattern: WrenString,
+        paths: WrenString,
+        regex: bool,
+        ignore_case: bool,
+        globs: WrenString,
+        context: f64,
+        hidden: bool,
+        ignore: bool,
+    ) -> Result<Value, String> {
+        let pattern = pattern.into_string().unwrap_or_default();
+        let paths = paths.into_string().unwrap_or_default();
+        let globs = globs.into_string().unwrap_or_default();
+        let regex = build_regex(&pattern, regex, ignore_case)?;
+        let walk = Walk::new(&paths, &globs, hidden, ignore)?;
+        let context = if context.is_finite() && context > 0.0 {
+            context as usize
+        } else {
+            0
+        };
+
+        let mut matches = Vec::new();
+        for (path, found) in walk.run(|path| grep_file(path, &regex, context)) {
+            matches.extend(found.into_iter().map(|m| m.into_value(&path)));
+        }
+        Ok(Value::List(matches))
+    }
+
+    /// `[[path, replacements, before, after], ...]` for every text file the
+    /// replacement would change; nothing is written here.
+    #[allow(clippy::too_many_arguments)]
+    fn replace_(
+        &self,
+        pattern: WrenString,
+        replacement: WrenString,
+        paths: WrenString,
+        regex: bool,
+        ignore_case: bool,
+        globs: WrenString,
+        hidden: bool,
+        ignore: bool,
+    ) -> Result<Value, String> {
+        let pattern = pattern.into_string().unwrap_or_default();
+        let replacement = replacement.into_string().unwrap_or_default();
+        let paths = paths.into_string().unwrap_or_default();
+        let globs = globs.into_string().unwrap_or_default();
+        let is_regex = regex;
+        let regex = build_regex(&pattern, is_regex, ignore_case)?;
+        let walk = Walk::new(&paths, &globs, hidden, ignore)?;
+
+        let changes = walk.run(|path| {
+            let before = String::from_utf8(read_text(path)?).ok()?;
+            let count = regex.find_iter(&before).count();
+            if count == 0 {
+                return None;
+            }
+            let after = if is_regex {
+                regex.replace_all(&before, replacement.as_str())
+            } else {
+                regex.replace_all(&before, NoExpand(&replacement))
+            }
+            .into_owned();
+            (after != before).then_some((count, before, after))
+        });
+        Ok(Value::List(
+            changes
+                .into_iter()
+                .map(|(path, (count, before, after))| {
+                    Value::List(vec![path.into(), count.into(), before.into(), after.into()])
+                })
+                .collect(),
+        ))
+    }
+}
+
+ruwren::wren_module! {
+    mod wrun_search {
+        pub crate::stdlib::search::Search;
+    }
+}
+
+pub fn publish_module(lib: &mut ModuleLibrary) {
+    wrun_search::publish_module(lib);
+}
@@ -0,0 +1,308 @@
+//! Colours and text attributes for `Style` in `wrun/print`.
+//!
+//! A style travels from Wren as a spec string: `;`-separated parts, each
+//! `fg=<color>`, `bg=<color>`, an attribute (`bold`, `dim`, `italic`,
+//! `underline`) or a bare colour meaning the foreground. So a plain colour
+//! name such as `"red"` is a valid spec too. Colours are written at the
+//! level `wrun/term` detected, with truecolor and 256-colour values
+//! downgraded to the nearest colour the terminal supports.
+
+#![allow(non_snake_case)]
+
+use crate::stdlib::term::{self, ColorLevel, Stream};
+use ruwren::foreign_v2::WrenString;
+use ruwren::{WrenObject, wren_impl};
+
+const NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "brightblack",
+    "brightred",
+    "brightgreen",
+    "brightyellow",
+    "brightblue",
+    "brightmagenta",
+    "brightcyan",
+    "brightwhite",
+];
+
+/// xterm's default palette, used to pick the nearest of the 16 colours.
+const PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Color {
+    /// One of the 16 basic colours; 8–15 are the bright variants.
+    Named(u8),
+    /// An entry of the 256-colour palette.
+    Index(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    pub const GREEN: Color = Color::Named(2);
+    pub const BLUE: Color = Color::Named(4);
+    pub const CYAN: Color = Color::Named(6);
+    pub const WHITE: Color = Color::Named(7);
+    pub const GRAY: Color = Color::Named(8);
+    pub const BRIGHT_RED: Color = Color::Named(9);
+    pub const BRIGHT_YELLOW: Color = Color::Named(11);
+
+    /// A name (`red`, `bright_red`, `gray`), `#rgb`, `#rrggbb`,
+    /// `rgb(r, g, b)` or a 256-colour index.
+    pub fn parse(text: &str) -> Result<Color, String> {
+        let text = text.trim();
+        let invalid = || {
+            format!(
+                "unknown color '{text}', expected a name, \"#rrggbb\", \"rgb(r, g, b)\" or 0-255"
+            )
+        };
+        if let Some(hex) = text.strip_prefix('#') {
+            let digits: Vec<u8> = hex
+                .chars()
+                .map(|c| c.to_digit(16).map(|d| d as u8))
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?;
+            return match digits[..] {
+                [r, g, b] => Ok(Color::Rgb(r * 17, g * 17, b * 17)),
+                [r1, r2, g1, g2, b1, b2] => {
+                    Ok(Color::Rgb(r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2))
+                }
+                _ => Err(invalid()),
+            };
+        }
+        let lower = text.to_ascii_lowercase();
+        if let Some(args) = lower
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let parts: Vec<u8> = args
+                .split(',')
+                .map(|part| part.trim().parse().ok())
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?;
+            return match parts[..] {
+                [r, g, b] => Ok(Color::Rgb(r, g, b)),
+                _ => Err(invalid()),
+            };
+        }
+        if let Ok(index) = lower.parse::<u8>() {
+            return Ok(Color::Index(index));
+        }
+        let name = lower.replace(['_', '-', ' '], "");
+        let name = match name.as_str() {
+            "gray" | "grey" => "brightblack",
+            other => other,
+        };
+        NAMES
+            .iter()
+            .position(|&known| known == name)
+            .map(|idx| Color::Named(idx as u8))
+            .ok_or_else(invalid)
+    }
+
+    fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Named(n) => PALETTE[n as usize],
+            Color::Index(n) if n < 16 => PALETTE[n as usize],
+            Color::Index(n) if n < 232 => {
+                let n = n - 16;
+                (
+                    CUBE[(n / 36) as usize],
+                    CUBE[(n / 6 % 6) as usize],
+                    CUBE[(n % 6) as usize],
+                )
+            }
+            Color::Index(n) => {
+                let level = 8 + (n - 232) * 10;
+                (level, level, level)
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    /// The nearest colour that `level` can show.
+    pub fn downgrade(self, level: ColorLevel) -> Color {
+        match (self, level) {
+            (Color::Rgb(r, g, b), ColorLevel::Ansi256) => Color::Index(rgb_to_index(r, g, b)),
+            (Color::Index(n), ColorLevel::Basic) if n < 16 => Color::Named(n),
+            (Color::Rgb(..) | Color::Index(_), ColorLevel::Basic) => {
+                let (r, g, b) = self.rgb();
+                Color::Named(nearest_named(r, g, b))
+            }
+            _ => self,
+        }
+    }
+
+    /// SGR parameters selecting this colour as foreground or background.
+    pub fn sgr(self, background: bool) -> String {
+        let base = if background { 40 } else { 30 };
+        match self {
+            Color::Named(n) if n < 8 => format!("{}", base + n),
+            Color::Named(n) => format!("{}", base + 60 + n - 8),
+            Color::Index(n) => format!("{};5;{n}", base + 8),
+            Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+        }
+    }
+
+    /// `sgr` for the colour level of stdout.
+    pub fn sgr_for_stdout(self, background: bool) -> String {
+        let level = term::color_level(Stream::Stdout).max(ColorLevel::Basic);
+        self.downgrade(level).sgr(background)
+    }
+}
+
+/// The closest entry of the 6x6x6 cube or the grey ramp.
+fn rgb_to_index(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..8 => 16,
+            249.. => 231,
+            _ => 232 + ((r as u16 - 8) * 24 / 247) as u8,
+        };
+    }
+    let step = |v: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&idx| (CUBE[idx] as i16 - v as i16).abs())
+            .unwrap_or(0) as u8
+    };
+    16 + 36 * step(r) + 6 * step(g) + step(b)
+}
+
+fn nearest_named(r: u8, g: u8, b: u8) -> u8 {
+    let distance = |(pr, pg, pb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, pr) + d(g, pg) + d(b, pb)
+    };
+    (0..16)
+        .min_by_key(|&idx| distance(PALETTE[idx]))
+        .unwrap_or(7) as u8
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn fg(color: Color) -> Style {
+        Style {
+            fg: Some(color),
+            ..Style::default()
+        }
+    }
+
+    /// Parse a spec such as `fg=#ff8800;bg=236;bold` or just `red`.
+    pub fn parse(spec: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+        for part in spec.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some(("fg", color)) => style.fg = Some(Color::parse(color)?),
+                Some(("bg", color)) => style.bg = Some(Color::parse(color)?),
+                Some((key, _)) => {
+                    return Err(format!("unknown style part '{key}', expected fg or bg"));
+                }
+                None => match part {
+                    "bold" => style.bold = true,
+                    "dim" => style.dim = true,
+                    "italic" => style.italic = true,
+                    "underline" => style.underline = true,
+                    "reset" | "default" => style.fg = None,
+                    color => style.fg = Some(Color::parse(color)?),
+                },
+            }
+        }
+        Ok(style)
+    }
+
+    /// The escape that switches this style on for stdout, or an empty
+    /// string when the style is empty or colour is off.
+    pub fn prefix(&self) -> String {
+        let level = term::color_level(Stream::Stdout);
+        if level == ColorLevel::None {
+            return String::new();
+        }
+        let mut params = Vec::new();
+        for (on, code) in [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+        ] {
+            if on {
+                params.push(code.to_string());
+            }
+        }
+        if let Some(fg) = self.fg {
+            params.push(fg.downgrade(level).sgr(false));
+        }
+        if let Some(bg) = self.bg {
+            params.push(bg.downgrade(level).sgr(true));
+        }
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", params.join(";"))
+        }
+    }
+
+    /// `text` in this style, followed by a reset.
+    pub fn apply(&self, text: &str) -> String {
+        let prefix = self.prefix();
+        if prefix.is_empty() {
+            text.to_string()
+        } else {
+            format!("{prefix}{text}\x1b[0m")
+        }
+    }
+}
+
+/// Parse a spec from Wren, prefixing errors with `Style: `.
+pub fn parse_spec(spec: WrenString) -> Result<Style, String> {
+    let spec = spec.into_string().unwrap_or_default();
+    Style::parse(&spec).map_err(|e| format!("Style: {e}"))
+}
+
+#[derive(WrenObject, Default)]
+pub struct StyleInternal;
+
+#[wren_impl]
+impl StyleInternal {
+    fn check(&self, spec: WrenString) -> Result<(), String> {
+        parse_spec(spec).map(|_| ())
+    }
+
+    fn apply(&self, spec: WrenString, text: WrenString) -> Result<String, String> {
+        let style = parse_spec(spec)?;
+        Ok(style.apply(&text.into_string().unwrap_or_default()))
+    }
+}
@@ -0,0 +1,287 @@
+//! Terminal detection and the colour policy behind `wrun/term`.
+//!
+//! Renderers build ANSI output and pass it through `paint` (or ask
+//! `use_color`) before printing. Colour is decided in this order:
+//! `--color=always|never` (or `Term.setColorMode`), then `FORCE_COLOR`,
+//! then `NO_COLOR`, then whether the stream is a terminal and what `TERM`
+//! and `COLORTERM` say it supports.
+
+#![allow(non_snake_case)]
+
+use ruwren::foreign_v2::WrenString;
+use ruwren::{ModuleLibrary, WrenObject, wren_impl};
+use std::io::{IsTerminal, stderr, stdin, stdout};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "stdin" => Some(Stream::Stdin),
+            "stdout" => Some(Stream::Stdout),
+            "stderr" => Some(Stream::Stderr),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_tty(stream: Stream) -> bool {
+    match stream {
+        Stream::Stdin => stdin().is_terminal(),
+        Stream::Stdout => stdout().is_terminal(),
+        Stream::Stderr => stderr().is_terminal(),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorLevel {
+    None,
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorLevel::None => "none",
+            ColorLevel::Basic => "16",
+            ColorLevel::Ansi256 => "256",
+            ColorLevel::TrueColor => "truecolor",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Auto => "auto",
+            ColorMode::Always => "always",
+            ColorMode::Never => "never",
+        }
+    }
+}
+
+static COLOR_MODE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_color_mode(mode: ColorMode) {
+    COLOR_MODE.store(mode as u8, Ordering::SeqCst);
+}
+
+pub fn color_mode() -> ColorMode {
+    match COLOR_MODE.load(Ordering::SeqCst) {
+        1 => ColorMode::Always,
+        2 => ColorMode::Never,
+        _ => ColorMode::Auto,
+    }
+}
+
+fn env_set(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// What the terminal claims to support, from `COLORTERM` and `TERM`.
+fn supported_level() -> ColorLevel {
+    if let Some(colorterm) = env_set("COLORTERM")
+        && (colorterm == "truecolor" || colorterm == "24bit")
+    {
+        return ColorLevel::TrueColor;
+    }
+    match env_set("TERM") {
+        Some(term) if term == "dumb" => ColorLevel::None,
+        Some(term) if term.contains("256color") => ColorLevel::Ansi256,
+        _ => ColorLevel::Basic,
+    }
+}
+
+/// `FORCE_COLOR`: `0`/`false` turns colour off, `1`–`3` pick a level and
+/// any other value means basic colour.
+fn forced_level() -> Option<ColorLevel> {
+    let value = std::env::var("FORCE_COLOR").ok()?;
+    Some(match value.as_str() {
+        "0" | "false" => ColorLevel::None,
+        "2" => ColorLevel::Ansi256,
+        "3" => ColorLevel::TrueColor,
+        _ => supported_level().max(ColorLevel::Basic),
+    })
+}
+
+/// Colours to use for output on `stream`.
+pub fn color_level(stream: Stream) -> ColorLevel {
+    match color_mode() {
+        ColorMode::Never => ColorLevel::None,
+        ColorMode::Always => supported_level().max(ColorLevel::Basic),
+        ColorMode::Auto => {
+            if let Some(level) = forced_level() {
+                level
+            } else if env_set("NO_COLOR").is_some() || !is_tty(stream) {
+                ColorLevel::None
+            } else {
+                supported_level()
+            }
+        }
+    }
+}
+
+pub fn use_color(stream: Stream) -> bool {
+    color_level(stream) != ColorLevel::None
+}
+
+/// `text` without its colour and style (SGR) sequences. Cursor movement
+/// and other escapes are kept.
+pub fn strip_color(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find(|c: char| !(c.is_ascii_digit() || c == ';'))
+            .unwrap_or(after.len());
+        if after[end..].starts_with('m') {
+            rest = &after[end + 1..];
+        } else {
+            out.push_str(&rest[start..start + 2]);
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// ANSI-coloured `text` as it should be written to `stream`.
+pub fn paint_for(stream: Stream, text: &str) -> String {
+    if use_color(stream) {
+        text.to_string()
+    } else {
+        strip_color(text)
+    }
+}
+
+/// `paint_for` stdout, where nearly all output goes.
+pub fn paint(text: &str) -> String {
+    paint_for(Stream::Stdout, text)
+}
+
+#[cfg(unix)]
+fn window_size() -> Option<libc::winsize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    ok.then_some(size)
+}
+
+fn env_size(key: &str) -> Option<usize> {
+    std::env::var(key)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&size| size > 0)
+}
+
+/// Columns of the terminal on stdout, falling back to `$COLUMNS`.
+pub fn terminal_width() -> Option<usize> {
+    #[cfg(unix)]
+    if let Some(size) = window_size()
+        && size.ws_col > 0
+    {
+        return Some(size.ws_col as usize);
+    }
+    env_size("COLUMNS")
+}
+
+/// Rows of the terminal on stdout, falling back to `$LINES`.
+pub fn terminal_height() -> Option<usize> {
+    #[cfg(unix)]
+    if let Some(size) = window_size()
+        && size.ws_row > 0
+    {
+        return Some(size.ws_row as usize);
+    }
+    env_size("LINES")
+}
+
+#[derive(WrenObject, Default)]
+pub struct TermInternal;
+
+#[wren_impl]
+impl TermInternal {
+    fn isTty(&self, stream: WrenString) -> Result<bool, String> {
+        let name = stream.into_string().unwrap_or_default();
+        Stream::parse(&name).map(is_tty).ok_or_else(|| {
+            format!("Term: unknown stream '{name}', expected \"stdin\", \"stdout\" or \"stderr\"")
+        })
+    }
+
+    fn width(&self) -> Option<f64> {
+        terminal_width().map(|width| width as f64)
+    }
+
+    fn height(&self) -> Option<f64> {
+        terminal_height().map(|height| height as f64)
+    }
+
+    fn colorLevel(&self) -> String {
+        color_level(Stream::Stdout).name().to_string()
+    }
+
+    fn colorLevelFor(&self, stream: WrenString) -> Result<String, String> {
+        let name = stream.into_string().unwrap_or_default();
+        Stream::parse(&name)
+            .map(|stream| color_level(stream).name().to_string())
+            .ok_or_else(|| {
+                format!(
+                    "Term: unknown stream '{name}', expected \"stdin\", \"stdout\" or \"stderr\""
+                )
+            })
+    }
+
+    fn colorMode(&self) -> String {
+        color_mode().name().to_string()
+    }
+
+    fn setColorMode(&self, mode: WrenString) -> Result<(), String> {
+        let name = mode.into_string().unwrap_or_default();
+        let mode = ColorMode::parse(&name).ok_or_else(|| {
+            format!("Term: unknown color mode '{name}', expected \"auto\", \"always\" or \"never\"")
+        })?;
+        set_color_mode(mode);
+        Ok(())
+    }
+
+    fn stripColor(&self, text: WrenString) -> String {
+        strip_color(&String::from_utf8_lossy(text.bytes()))
+    }
+}
+
+ruwren::wren_module! {
+    mod wrun_term {
+        pub crate::stdlib::term::TermInternal;
+    }
+}
+
+pub fn publish_module(lib: &mut ModuleLibrary) {
+    wrun_term::publish_module(lib);
+}
@@ -0,0 +1,550 @@
+//! Directory tree helpers behind `Dir.copy`, `Dir.sync`, `File.move`,
+//! `Diff.dirs`, archive creation and checksum manifests.
+//!
+//! Both copy and sync first build a plan (a list of `Op`s relative to the
+//! source/destination roots) and then either execute it or, under
+//! `--dry-run`, print it. That keeps the dry-run output exactly what a real
+//! run would do.
+
+use crate::stdlib::process::is_dry_run;
+use crate::stdlib::value::Value;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Default)]
+pub struct TreeOptions {
+    pub overwrite: bool,
+    pub preserve_times: bool,
+    pub delete: bool,
+    pub checksum: bool,
+    pub exclude: Option<GlobSet>,
+}
+
+/// Build a matcher from `\x00`-separated glob patterns (how the Wren side
+/// passes lists). An invalid pattern is an error naming it.
+pub fn build_excludes(patterns: &str) -> Result<Option<GlobSet>, String> {
+    let patterns: Vec<&str> = patterns.split('\x00').filter(|p| !p.is_empty()).collect();
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|err| format!("invalid exclude glob: {err}"))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|err| format!("invalid exclude glob: {err}"))
+}
+
+/// Patterns match either the path relative to the tree root or the bare
+/// entry name, so `"*.log"`, `"build/tmp"` and `"node_modules"` all work.
+fn is_excluded(options: &TreeOptions, rel: &Path) -> bool {
+    let Some(exclude) = &options.exclude else {
+        return false;
+    };
+    exclude.is_match(rel) || rel.file_name().is_some_and(|name| exclude.is_match(name))
+}
+
+enum Op {
+    Mkdir(PathBuf),
+    Copy { rel: PathBuf, update: bool },
+    Remove(PathBuf),
+}
+
+#[derive(Default)]
+pub struct Summary {
+    pub copied: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub unchanged: usize,
+    pub errors: Vec<String>,
+    pub dry_run: bool,
+}
+
+impl Summary {
+    pub fn into_value(self) -> Value {
+        let ok = self.errors.is_empty();
+        Value::map()
+            .with("copied", self.copied)
+            .with("updated", self.updated)
+            .with("deleted", self.deleted)
+            .with("unchanged", self.unchanged)
+            .with("errors", self.errors)
+            .with("dryRun", self.dry_run)
+            .with("ok", ok)
+    }
+}
+
+/// `root.join(rel)` without the trailing separator `join("")` adds.
+fn under(root: &Path, rel: &Path) -> PathBuf {
+    if rel.as_os_str().is_empty() {
+        root.to_path_buf()
+    } else {
+        root.join(rel)
+    }
+}
+
+pub fn display(rel: &Path) -> String {
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+/// `path` as a relative path with `.` components dropped, or `None` if it
+/// is absolute or climbs out with `..`. The result may be empty (`./`).
+pub fn relative_path(path: &str) -> Option<PathBuf> {
+    let mut safe = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(safe)
+}
+
+fn sorted_entries(dir: &Path) -> io::Result<Vec<fs::DirEntry>> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)?.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.file_name());
+    Ok(entries)
+}
+
+/// Walk `src` depth-first (sorted, not following symlinks) and call `visit`
+/// with each entry's path relative to `src`.
+pub fn walk(
+    src: &Path,
+    rel: &Path,
+    options: &TreeOptions,
+    visit: &mut dyn FnMut(&Path, &fs::Metadata),
+) -> io::Result<()> {
+    for entry in sorted_entries(&src.join(rel))? {
+        let entry_rel = rel.join(entry.file_name());
+        if is_excluded(options, &entry_rel) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        visit(&entry_rel, &meta);
+        if meta.is_dir() {
+            walk(src, &entry_rel, options, visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn plan_copy(src: &Path, dst: &Path, options: &TreeOptions) -> io::Result<Vec<Op>> {
+    let mut ops = Vec::new();
+    if !dst.is_dir() {
+        ops.push(Op::Mkdir(PathBuf::new()));
+    }
+    walk(src, Path::new(""), options, &mut |rel, meta| {
+        let target = dst.join(rel);
+        let exists = fs::symlink_metadata(&target).is_ok();
+        if meta.is_dir() {
+            if !target.is_dir() {
+                ops.push(Op::Mkdir(rel.to_path_buf()));
+            }
+        } else if !exists {
+            ops.push(Op::Copy {
+                rel: rel.to_path_buf(),
+                update: false,
+            });
+        } else if options.overwrite {
+            ops.push(Op::Copy {
+                rel: rel.to_path_buf(),
+                update: true,
+            });
+        }
+    })?;
+    Ok(ops)
+}
+
+fn plan_sync(
+    src: &Path,
+    dst: &Path,
+    options: &TreeOptions,
+    summary: &mut Summary,
+) -> io::Result<Vec<Op>> {
+    let mut ops = Vec::new();
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    if !dst.is_dir() {
+        ops.push(Op::Mkdir(PathBuf::new()));
+    }
+
+    walk(src, Path::new(""), options, &mut |rel, meta| {
+        seen.insert(rel.to_path_buf());
+        let source = src.join(rel);
+        let target = dst.join(rel);
+        let Ok(existing) = fs::symlink_metadata(&target) else {
+            ops.push(if meta.is_dir() {
+                Op::Mkdir(rel.to_path_buf())
+            } else {
+                Op::Copy {
+                    rel: rel.to_path_buf(),
+                    update: false,
+                }
+            });
+            return;
+        };
+
+        if meta.is_dir() {
+            if !existing.is_dir() {
+                ops.push(Op::Remove(rel.to_path_buf()));
+                ops.push(Op::Mkdir(rel.to_path_buf()));
+            }
+        } else if differs(&source, meta, &target, &existing, options.checksum) {
+            ops.push(Op::Copy {
+                rel: rel.to_path_buf(),
+                update: true,
+            });
+        } else {
+            summary.unchanged += 1;
+        }
+    })?;
+
+    if options.delete && dst.is_dir() {
+        let mut extras = Vec::new();
+        collect_extras(dst, Path::new(""), options, &seen, &mut extras)?;
+        ops.extend(extras.into_iter().map(Op::Remove));
+    }
+    Ok(ops)
+}
+
+/// Destination entries with no counterpart in the source. A missing
+/// directory is reported once, not per file inside it.
+fn collect_extras(
+    dst: &Path,
+    rel: &Path,
+    options: &TreeOptions,
+    seen: &HashSet<PathBuf>,
+    extras: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    for entry in sorted_entries(&dst.join(rel))? {
+        let entry_rel = rel.join(entry.file_name());
+        if is_excluded(options, &entry_rel) {
+            continue;
+        }
+        if !seen.contains(&entry_rel) {
+            extras.push(entry_rel);
+        } else if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_extras(dst, &entry_rel, options, seen, extras)?;
+        }
+    }
+    Ok(())
+}
+
+fn differs(
+    source: &Path,
+    source_meta: &fs::Metadata,
+    target: &Path,
+    target_meta: &fs::Metadata,
+    checksum: bool,
+) -> bool {
+    if source_meta.file_type().is_symlink() || target_meta.file_type().is_symlink() {
+        return fs::read_link(source).ok() != fs::read_link(target).ok();
+    }
+    if !target_meta.is_file() || source_meta.len() != target_meta.len() {
+        return true;
+    }
+    if checksum {
+        return !same_content(source, target).unwrap_or(false);
+    }
+    source_meta.modified().ok() != target_meta.modified().ok()
+}
+
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = io::BufReader::new(fs::File::open(a)?);
+    let mut b = io::BufReader::new(fs::File::open(b)?);
+    let mut buf_a = [0u8; 64 * 1024];
+    let mut buf_b = [0u8; 64 * 1024];
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(b.read(&mut buf_b)? == 0);
+        }
+        if b.read_exact(&mut buf_b[..n]).is_err() || buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+pub fn remove_any(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, to)
+    }
+    #[cfg(windows)]
+    {
+        if from.is_dir() {
+            std::os::windows::fs::symlink_dir(target, to)
+        } else {
+            std::os::windows::fs::symlink_file(target, to)
+        }
+    }
+}
+
+/// Copy one file or symlink, replacing whatever is at `to` (never writing
+/// through an existing symlink).
+pub fn copy_entry(from: &Path, to: &Path, preserve_times: bool) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    if fs::symlink_metadata(to).is_ok() {
+        remove_any(to)?;
+    }
+    if meta.file_type().is_symlink() {
+        return copy_symlink(from, to);
+    }
+    fs::copy(from, to)?;
+    if preserve_times {
+        let mut times = fs::FileTimes::new();
+        if let Ok(modified) = meta.modified() {
+            times = times.set_modified(modified);
+        }
+        if let Ok(accessed) = meta.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        // fs::copy already applied the source's permissions, so a read-only
+        // file can only be reopened for reading; the owner may still set
+        // times through that handle.
+        fs::OpenOptions::new()
+            .write(true)
+            .open(to)
+            .or_else(|_| fs::File::open(to))?
+            .set_times(times)?;
+    }
+    Ok(())
+}
+
+fn execute(ops: Vec<Op>, src: &Path, dst: &Path, options: &TreeOptions, summary: &mut Summary) {
+    let dry_run = summary.dry_run;
+    for op in ops {
+        let (label, rel, result) = match &op {
+            Op::Mkdir(rel) => {
+                if dry_run {
+                    println!("[dry-run] mkdir {}", under(dst, rel).display());
+                    continue;
+                }
+                ("mkdir", rel, fs::create_dir_all(under(dst, rel)))
+            }
+            Op::Copy { rel, update } => {
+                let (from, to) = (src.join(rel), dst.join(rel));
+                if *update {
+                    summary.updated.push(display(rel));
+                } else {
+                    summary.copied.push(display(rel));
+                }
+                if dry_run {
+                    let verb = if *update { "update" } else { "copy" };
+                    println!("[dry-run] {} {} -> {}", verb, from.display(), to.display());
+                    continue;
+                }
+                ("copy", rel, copy_entry(&from, &to, options.preserve_times))
+            }
+            Op::Remove(rel) => {
+                summary.deleted.push(display(rel));
+                if dry_run {
+                    println!("[dry-run] delete {}", dst.join(rel).display());
+                    continue;
+                }
+                ("delete", rel, remove_any(&dst.join(rel)))
+            }
+        };
+        if let Err(err) = result {
+            summary
+                .errors
+                .push(format!("{} {}: {}", label, display(rel), err));
+        }
+    }
+}
+
+pub fn copy_tree(src: &Path, dst: &Path, options: &TreeOptions) -> Summary {
+    let mut summary = Summary {
+        dry_run: is_dry_run(),
+        ..Summary::default()
+    };
+    if !src.is_dir() {
+        summary
+            .errors
+            .push(format!("{}: not a directory", src.display()));
+        return summary;
+    }
+    match plan_copy(src, dst, options) {
+        Ok(ops) => execute(ops, src, dst, options, &mut summary),
+        Err(err) => summary.errors.push(format!("{}: {}", src.display(), err)),
+    }
+    summary
+}
+
+pub fn sync_tree(src: &Path, dst: &Path, options: &TreeOptions) -> Summary {
+    let mut summary = Summary {
+        dry_run: is_dry_run(),
+        ..Summary::default()
+    };
+    if !src.is_dir() {
+        summary
+            .errors
+            .push(format!("{}: not a directory", src.display()));
+        return summary;
+    }
+    match plan_sync(src, dst, options, &mut summary) {
+        Ok(ops) => execute(ops, src, dst, options, &mut summary),
+        Err(err) => summary.errors.push(format!("{}: {}", src.display(), err)),
+    }
+    summary
+}
+
+/// `rename`, falling back to copy + delete when `from` and `to` are on
+/// different file systems.
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if is_dry_run() {
+        println!("[dry-run] move {} -> {}", from.display(), to.display());
+        return Ok(());
+    }
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {}
+        other => return other,
+    }
+
+    let meta = fs::symlink_metadata(from)?;
+    if meta.is_dir() {
+        let options = TreeOptions {
+            overwrite: true,
+            preserve_times: true,
+            ..TreeOptions::default()
+        };
+        let summary = copy_tree(from, to, &options);
+        if let Some(err) = summary.errors.first() {
+            return Err(io::Error::other(err.clone()));
+        }
+        fs::remove_dir_all(from)
+    } else {
+        copy_entry(from, to, true)?;
+        fs::remove_file(from)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl EntryKind {
+    fn of(meta: &fs::Metadata) -> EntryKind {
+        if meta.file_type().is_symlink() {
+            EntryKind::Symlink
+        } else if meta.is_dir() {
+            EntryKind::Dir
+        } else {
+            EntryKind::File
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Dir => "dir",
+            EntryKind::Symlink => "symlink",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TreeChange {
+    Added,
+    Removed,
+    Changed,
+    TypeChanged,
+}
+
+impl TreeChange {
+    pub fn name(self) -> &'static str {
+        match self {
+            TreeChange::Added => "added",
+            TreeChange::Removed => "removed",
+            TreeChange::Changed => "changed",
+            TreeChange::TypeChanged => "typeChanged",
+        }
+    }
+}
+
+pub struct TreeDiffEntry {
+    pub rel: PathBuf,
+    pub change: TreeChange,
+    pub left: Option<EntryKind>,
+    pub right: Option<EntryKind>,
+}
+
+fn tree_entries(root: &Path, options: &TreeOptions) -> io::Result<BTreeMap<PathBuf, EntryKind>> {
+    let mut entries = BTreeMap::new();
+    walk(root, Path::new(""), options, &mut |rel, meta| {
+        entries.insert(rel.to_path_buf(), EntryKind::of(meta));
+    })?;
+    Ok(entries)
+}
+
+/// Entries that differ between two trees, sorted by path. Files are
+/// compared by content and symlinks by target. A directory that was added,
+/// removed or replaced is reported once, without its contents.
+pub fn compare_trees(
+    left: &Path,
+    right: &Path,
+    options: &TreeOptions,
+) -> io::Result<Vec<TreeDiffEntry>> {
+    let left_entries = tree_entries(left, options)?;
+    let right_entries = tree_entries(right, options)?;
+    let paths: BTreeSet<&PathBuf> = left_entries.keys().chain(right_entries.keys()).collect();
+
+    let mut changes = Vec::new();
+    let mut covered: Option<&PathBuf> = None;
+    for rel in paths {
+        if covered.is_some_and(|dir| rel.starts_with(dir)) {
+            continue;
+        }
+        let old = left_entries.get(rel).copied();
+        let new = right_entries.get(rel).copied();
+        let change = match (old, new) {
+            (None, Some(_)) => TreeChange::Added,
+            (Some(_), None) => TreeChange::Removed,
+            (Some(a), Some(b)) if a != b => TreeChange::TypeChanged,
+            (Some(EntryKind::File), _) => {
+                if same_content(&left.join(rel), &right.join(rel))? {
+                    continue;
+                }
+                TreeChange::Changed
+            }
+            (Some(EntryKind::Symlink), _) => {
+                if fs::read_link(left.join(rel)).ok() == fs::read_link(right.join(rel)).ok() {
+                    continue;
+                }
+                TreeChange::Changed
+            }
+            _ => continue,
+        };
+        if old == Some(EntryKind::Dir) || new == Some(EntryKind::Dir) {
+            covered = Some(rel);
+        }
+        changes.push(TreeDiffEntry {
+            rel: rel.clone(),
+            change,
+            left: old,
+            right: new,
+        });
+    }
+    Ok(changes)
+}
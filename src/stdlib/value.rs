@@ -0,0 +1,135 @@
+use ruwren::foreign_v2::WrenTo;
+use ruwren::{SlotId, VM};
+
+/// Maximum nesting of lists/maps a `Value` can hand to Wren. Each level
+/// borrows two scratch slots (key + value).
+const MAX_DEPTH: usize = 8;
+
+/// Loosely-typed data returned to Wren as real Lists/Maps, for foreign
+/// methods whose result is a record (stat maps, summaries, ...).
+#[derive(Clone, Debug, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn map() -> Self {
+        Value::Map(Vec::new())
+    }
+
+    /// Builder-style insert for `Value::Map`; ignored for other variants.
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        if let Value::Map(entries) = &mut self {
+            entries.push((key.to_string(), value.into()));
+        }
+        self
+    }
+
+    fn write(self, vm: &VM, slot: SlotId, scratch: SlotId, depth: usize) {
+        match self {
+            Value::Null => vm.set_slot_null(slot),
+            Value::Bool(b) => vm.set_slot_bool(slot, b),
+            Value::Num(n) => vm.set_slot_double(slot, n),
+            Value::Str(s) => vm.set_slot_string(slot, s),
+            Value::List(items) => {
+                vm.set_slot_new_list(slot);
+                if depth >= MAX_DEPTH {
+                    return;
+                }
+                for (idx, item) in items.into_iter().enumerate() {
+                    item.write(vm, scratch, scratch + 2, depth + 1);
+                    vm.insert_in_list(slot, idx as i32, scratch);
+                }
+            }
+            Value::Map(entries) => {
+                vm.set_slot_new_map(slot);
+                if depth >= MAX_DEPTH {
+                    return;
+                }
+                for (key, value) in entries {
+                    value.write(vm, scratch + 1, scratch + 2, depth + 1);
+                    vm.set_slot_string(scratch, key);
+                    vm.set_map_value(slot, scratch, scratch + 1);
+                }
+            }
+        }
+    }
+}
+
+impl WrenTo for Value {
+    const SCRATCH_SPACE: usize = 2 * MAX_DEPTH;
+
+    fn to_vm(self, vm: &VM, slot: SlotId, scratch_start: SlotId) {
+        self.write(vm, slot, scratch_start, 0);
+    }
+}
+
+/// Raw bytes handed to Wren as a String (Wren strings are byte strings, so
+/// non-UTF-8 content survives a round trip).
+pub struct RawText(pub Vec<u8>);
+
+impl WrenTo for RawText {
+    fn to_vm(self, vm: &VM, slot: SlotId, _scratch_start: SlotId) {
+        vm.set_slot_bytes(slot, &self.0);
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Num(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Num(value as f64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Num(value as f64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Num(value as f64)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Value::Null)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::List(value.into_iter().map(Into::into).collect())
+    }
+}
@@ -0,0 +1,425 @@
+//! `wrun --watch`: re-run a script whenever it, the modules it imports or any
+//! extra watched paths change.
+//!
+//! Each run is a separate `wrun` child process, so every run gets a fresh VM
+//! and a script that calls `Process.exit` or never returns cannot take the
+//! watcher down with it. On unix the child leads its own process group, which
+//! lets us stop everything it spawned before starting the next run.
+
+use crate::stdlib::term::{self, Stream};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitCode, ExitStatus};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
+
+const DIM: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[3J\x1b[H";
+const CHILD_POLL: Duration = Duration::from_millis(50);
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+pub struct WatchOptions {
+    pub script: PathBuf,
+    pub script_dir: PathBuf,
+    pub paths: Vec<PathBuf>,
+    pub clear: bool,
+    pub debounce: Duration,
+    /// Arguments for each child `wrun` run (the CLI minus the watch flags).
+    pub run_args: Vec<OsString>,
+}
+
+enum Outcome {
+    Changed(Vec<PathBuf>),
+    Exited(Option<ExitStatus>),
+}
+
+/// Files and directories observed for one run. Imports are re-scanned before
+/// every run so newly added modules are picked up.
+struct Targets {
+    files: BTreeSet<PathBuf>,
+    trees: Vec<PathBuf>,
+    dirs: BTreeSet<PathBuf>,
+}
+
+type EventRx = Receiver<notify::Result<Event>>;
+
+pub fn run(options: WatchOptions) -> ExitCode {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Error locating wrun executable: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    sys::install_interrupt_handler();
+    let mut changed: Vec<PathBuf> = Vec::new();
+
+    loop {
+        let targets = collect_targets(&options);
+        let (tx, rx) = channel();
+        let mut watcher = match RecommendedWatcher::new(
+            move |res| {
+                let _ = tx.send(res);
+            },
+            Config::default(),
+        ) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Error starting watcher: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        for dir in &targets.dirs {
+            let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+        }
+        for tree in &targets.trees {
+            let _ = watcher.watch(tree, RecursiveMode::Recursive);
+        }
+
+        if options.clear {
+            print!("{CLEAR_SCREEN}");
+            let _ = std::io::stdout().flush();
+        }
+        if changed.is_empty() {
+            status(&format!(
+                "running {} (watching {} path(s))",
+                options.script.display(),
+                targets.files.len() + targets.trees.len()
+            ));
+        } else {
+            status(&format!("{} changed, re-running", describe_changes(&changed)));
+        }
+
+        let mut command = Command::new(&exe);
+        command.args(&options.run_args);
+        sys::isolate(&mut command);
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                status(&format!("failed to start run: {}", e));
+                changed = wait_for_change(&rx, &targets, options.debounce);
+                continue;
+            }
+        };
+        sys::started(&child);
+
+        match supervise(&mut child, &rx, &targets, options.debounce) {
+            Outcome::Changed(paths) => {
+                sys::terminate(&mut child);
+                changed = paths;
+            }
+            Outcome::Exited(exit) => {
+                sys::reclaim_terminal();
+                if exit.as_ref().is_some_and(sys::interrupted) {
+                    sys::kill_group(&child);
+                    return ExitCode::from(130);
+                }
+                match exit.and_then(|s| s.code()) {
+                    Some(0) => status("finished, waiting for changes"),
+                    Some(code) => status(&format!("exited with code {}, waiting for changes", code)),
+                    None => status("terminated, waiting for changes"),
+                }
+                changed = wait_for_change(&rx, &targets, options.debounce);
+                sys::kill_group(&child);
+            }
+        }
+    }
+}
+
+fn status(message: &str) {
+    let line = format!("{DIM}[watch] {message}{RESET}");
+    eprintln!("{}", term::paint_for(Stream::Stderr, &line));
+}
+
+fn describe_changes(paths: &[PathBuf]) -> String {
+    let first = paths
+        .first()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    match paths.len() {
+        0 | 1 => first,
+        n => format!("{} (+{} more)", first, n - 1),
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        return canonical;
+    }
+    std::env::current_dir()
+        .map(|cwd| cwd.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn collect_targets(options: &WatchOptions) -> Targets {
+    let mut files = BTreeSet::new();
+    let mut trees = Vec::new();
+
+    let script = absolute(&options.script);
+    files.insert(script.clone());
+    collect_imports(&script, &options.script_dir, &mut files);
+
+    for path in &options.paths {
+        let path = absolute(path);
+        if path.is_dir() {
+            trees.push(path);
+        } else {
+            files.insert(path);
+        }
+    }
+
+    // Watch parent directories rather than the files themselves: editors often
+    // save by replacing the file, which would silently detach a per-file watch.
+    let dirs = files
+        .iter()
+        .filter_map(|f| f.parent().map(Path::to_path_buf))
+        .filter(|dir| !trees.iter().any(|tree| dir.starts_with(tree)))
+        .collect();
+
+    Targets { files, trees, dirs }
+}
+
+/// Follow `import "name"` statements the same way the script loader does:
+/// every user module resolves to `<script dir>/<name>.wren`.
+fn collect_imports(module: &Path, script_dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let Ok(source) = std::fs::read_to_string(module) else {
+        return;
+    };
+    for name in imported_modules(&source) {
+        if name.starts_with("wrun/") || name == "random" || name == "meta" {
+            continue;
+        }
+        let path = script_dir.join(&name).with_extension("wren");
+        if !path.is_file() {
+            continue;
+        }
+        let path = absolute(&path);
+        if files.insert(path.clone()) {
+            collect_imports(&path, script_dir, files);
+        }
+    }
+}
+
+fn imported_modules(source: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = source[offset..].find("import") {
+        let start = offset + found;
+        offset = start + "import".len();
+
+        let at_boundary = source[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'));
+        let rest = source[offset..].trim_start();
+        if at_boundary
+            && let Some(quoted) = rest.strip_prefix('"')
+            && let Some(end) = quoted.find('"')
+        {
+            names.push(quoted[..end].to_string());
+        }
+    }
+    names
+}
+
+fn relevant_paths(event: &Event, targets: &Targets) -> Vec<PathBuf> {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .iter()
+        .filter(|path| {
+            targets.files.contains(*path) || targets.trees.iter().any(|tree| path.starts_with(tree))
+        })
+        .cloned()
+        .collect()
+}
+
+fn supervise(child: &mut Child, rx: &EventRx, targets: &Targets, debounce: Duration) -> Outcome {
+    loop {
+        match child.try_wait() {
+            Ok(Some(exit)) => return Outcome::Exited(Some(exit)),
+            Ok(None) => {}
+            Err(_) => return Outcome::Exited(child.wait().ok()),
+        }
+        match rx.recv_timeout(CHILD_POLL) {
+            Ok(Ok(event)) => {
+                let hits = relevant_paths(&event, targets);
+                if !hits.is_empty() {
+                    return Outcome::Changed(settle(rx, targets, debounce, hits));
+                }
+            }
+            Ok(Err(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => std::thread::sleep(CHILD_POLL),
+        }
+    }
+}
+
+fn wait_for_change(rx: &EventRx, targets: &Targets, debounce: Duration) -> Vec<PathBuf> {
+    while let Ok(result) = rx.recv() {
+        if let Ok(event) = result {
+            let hits = relevant_paths(&event, targets);
+            if !hits.is_empty() {
+                return settle(rx, targets, debounce, hits);
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Keep absorbing changes until nothing relevant has happened for `debounce`,
+/// so a burst of saves triggers a single re-run.
+fn settle(rx: &EventRx, targets: &Targets, debounce: Duration, first: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut hits: BTreeSet<PathBuf> = first.into_iter().collect();
+    let mut quiet_until = Instant::now() + debounce;
+    loop {
+        let now = Instant::now();
+        if now >= quiet_until {
+            break;
+        }
+        match rx.recv_timeout(quiet_until - now) {
+            Ok(Ok(event)) => {
+                let more = relevant_paths(&event, targets);
+                if !more.is_empty() {
+                    hits.extend(more);
+                    quiet_until = Instant::now() + debounce;
+                }
+            }
+            Ok(Err(_)) => {}
+            Err(_) => break,
+        }
+    }
+    hits.into_iter().collect()
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::{CHILD_POLL, KILL_GRACE};
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::{Child, Command, ExitStatus};
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+    use std::time::Instant;
+
+    static CHILD_GROUP: AtomicI32 = AtomicI32::new(0);
+    static OWNS_TERMINAL: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_interrupt(signal: libc::c_int) {
+        let group = CHILD_GROUP.load(Ordering::SeqCst);
+        unsafe {
+            if group > 0 {
+                libc::kill(-group, libc::SIGTERM);
+            }
+            libc::_exit(128 + signal);
+        }
+    }
+
+    pub fn install_interrupt_handler() {
+        let owns_terminal = unsafe {
+            libc::isatty(libc::STDIN_FILENO) == 1
+                && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+        };
+        OWNS_TERMINAL.store(owns_terminal, Ordering::SeqCst);
+
+        let handler = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        unsafe {
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
+        }
+    }
+
+    /// Start the run in a new process group and, when we are the terminal's
+    /// foreground job, hand the terminal over so the script can read input
+    /// and receive Ctrl-C itself.
+    pub fn isolate(command: &mut Command) {
+        let owns_terminal = OWNS_TERMINAL.load(Ordering::SeqCst);
+        unsafe {
+            command.pre_exec(move || {
+                libc::setpgid(0, 0);
+                if owns_terminal {
+                    set_foreground(libc::getpgrp());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    pub fn started(child: &Child) {
+        CHILD_GROUP.store(child.id() as i32, Ordering::SeqCst);
+    }
+
+    pub fn reclaim_terminal() {
+        if OWNS_TERMINAL.load(Ordering::SeqCst) {
+            unsafe { set_foreground(libc::getpgrp()) };
+        }
+    }
+
+    /// Stop a run that is still going: SIGTERM the whole group, then SIGKILL
+    /// whatever is left after a short grace period.
+    pub fn terminate(child: &mut Child) {
+        let group = child.id() as i32;
+        unsafe { libc::kill(-group, libc::SIGTERM) };
+        let deadline = Instant::now() + KILL_GRACE;
+        loop {
+            match child.try_wait() {
+                Ok(None) if Instant::now() < deadline => std::thread::sleep(CHILD_POLL),
+                Ok(None) => {
+                    unsafe { libc::kill(-group, libc::SIGKILL) };
+                    let _ = child.wait();
+                    break;
+                }
+                _ => break,
+            }
+        }
+        unsafe { libc::kill(-group, libc::SIGKILL) };
+        reclaim_terminal();
+    }
+
+    /// Processes spawned by a finished run may outlive it (servers started via
+    /// `Shell.spawn`); they are stopped right before the next run starts.
+    pub fn kill_group(child: &Child) {
+        unsafe { libc::kill(-(child.id() as i32), libc::SIGTERM) };
+    }
+
+    pub fn interrupted(status: &ExitStatus) -> bool {
+        status.signal() == Some(libc::SIGINT)
+    }
+
+    unsafe fn set_foreground(group: libc::pid_t) {
+        unsafe {
+            let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, group);
+            libc::signal(libc::SIGTTOU, previous);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::process::{Child, Command, ExitStatus};
+
+    pub fn install_interrupt_handler() {}
+
+    pub fn isolate(_command: &mut Command) {}
+
+    pub fn started(_child: &Child) {}
+
+    pub fn reclaim_terminal() {}
+
+    pub fn terminate(child: &mut Child) {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    pub fn kill_group(_child: &Child) {}
+
+    pub fn interrupted(_status: &ExitStatus) -> bool {
+        false
+    }
+}